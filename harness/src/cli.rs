@@ -0,0 +1,133 @@
+use std::{fmt, ops::RangeInclusive};
+
+use hydrocarbon_harness::Chapter;
+
+pub const MIN_CARBONS: usize = 2;
+// 古い章の行列は u32 に 2 ビットずつ詰めるため, 16 炭素までしか扱えない
+pub const MAX_CARBONS: usize = 16;
+
+pub const USAGE: &str = "\
+Usage: hydrocarbon-harness <CHAPTER_A> <CHAPTER_B> [OPTIONS]
+
+Runs two chapters on the same carbon counts and compares their isomers
+as sets of canonical forms. Chapters are named after their directories,
+e.g. p2_ch05 or p2_ch07-4.
+
+Options:
+  --carbons <RANGE>        Carbon counts to compare, e.g. 6, 2..8 or 2..=8 [default: 2..=7]
+  --show-diff              Print the isomers found by only one of the chapters
+  -h, --help               Print this message";
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub chapter_a: Chapter,
+    pub chapter_b: Chapter,
+    pub carbons: RangeInclusive<usize>,
+    pub show_diff: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    Help,
+    MissingChapter,
+    UnknownChapter(String),
+    UnknownOption(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+    CarbonsOutOfRange(RangeInclusive<usize>),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Help => write!(f, "{USAGE}"),
+            Self::MissingChapter => write!(f, "two chapters are required\n\n{USAGE}"),
+            Self::UnknownChapter(name) => {
+                let names: Vec<_> = Chapter::ALL.iter().map(|c| c.name()).collect();
+                write!(
+                    f,
+                    "unknown chapter `{name}`: expected one of {}",
+                    names.join(", ")
+                )
+            }
+            Self::UnknownOption(opt) => write!(f, "unknown option `{opt}`\n\n{USAGE}"),
+            Self::MissingValue(opt) => write!(f, "option `{opt}` requires a value"),
+            Self::InvalidValue(opt, value) => {
+                write!(f, "invalid value `{value}` for option `{opt}`")
+            }
+            Self::CarbonsOutOfRange(range) => write!(
+                f,
+                "carbon counts {}..={} are out of range: supported range is {MIN_CARBONS}..={MAX_CARBONS}",
+                range.start(),
+                range.end()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, CliError> {
+    let mut chapters = Vec::new();
+    let mut carbons = 2..=7;
+    let mut show_diff = false;
+    while let Some(arg) = args.next() {
+        // `--option=value` と `--option value` の両方を受け付ける
+        let (opt, inline_value) = match arg.split_once('=') {
+            Some((opt, value)) if opt.starts_with("--") => {
+                (opt.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        match opt.as_str() {
+            "--carbons" => {
+                let value = inline_value
+                    .or_else(|| args.next())
+                    .ok_or(CliError::MissingValue("--carbons"))?;
+                carbons = parse_carbons(&value)?;
+            }
+            "--show-diff" => show_diff = true,
+            "-h" | "--help" => return Err(CliError::Help),
+            _ if opt.starts_with('-') => return Err(CliError::UnknownOption(opt)),
+            _ => {
+                let chapter = Chapter::from_name(&opt)
+                    .ok_or_else(|| CliError::UnknownChapter(opt.clone()))?;
+                chapters.push(chapter);
+            }
+        }
+    }
+
+    let [chapter_a, chapter_b] = chapters[..] else {
+        return Err(CliError::MissingChapter);
+    };
+    Ok(Args {
+        chapter_a,
+        chapter_b,
+        carbons,
+        show_diff,
+    })
+}
+
+fn parse_carbons(s: &str) -> Result<RangeInclusive<usize>, CliError> {
+    let invalid = || CliError::InvalidValue("--carbons", s.to_string());
+    let range = if let Some((start, end)) = s.split_once("..=") {
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = end.parse().map_err(|_| invalid())?;
+        start..=end
+    } else if let Some((start, end)) = s.split_once("..") {
+        let start = start.parse().map_err(|_| invalid())?;
+        let end: usize = end.parse().map_err(|_| invalid())?;
+        start..=end.checked_sub(1).ok_or_else(invalid)?
+    } else {
+        let n = s.parse().map_err(|_| invalid())?;
+        n..=n
+    };
+
+    if range.is_empty() {
+        return Err(invalid());
+    }
+    if *range.start() < MIN_CARBONS || *range.end() > MAX_CARBONS {
+        return Err(CliError::CarbonsOutOfRange(range));
+    }
+    Ok(range)
+}
@@ -0,0 +1,88 @@
+//! 各章の列挙結果を比べる道具.
+//! 参照表との比較は各章のクレートが `hydrocarbon-testdata` を使って自身で行う
+
+mod module;
+
+pub use module::{Chapter, Comparison, Molecule};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_edges(num_carbons: usize, edges: &[(usize, usize, u8)]) -> Molecule {
+        Molecule::from_fn(num_carbons, |i, j| {
+            edges
+                .iter()
+                .find(|&&(a, b, _)| (a, b) == (i, j) || (a, b) == (j, i))
+                .map_or(0, |&(_, _, order)| order)
+        })
+    }
+
+    #[test]
+    fn canonical_form_ignores_labels() {
+        // 1-ブテンの番号を付け替えたもの
+        let a = from_edges(4, &[(0, 1, 2), (1, 2, 1), (2, 3, 1)]);
+        let b = from_edges(4, &[(3, 2, 2), (2, 0, 1), (0, 1, 1)]);
+        assert_eq!(a.canonicalize::<4>(), b.canonicalize::<4>());
+    }
+
+    #[test]
+    fn canonical_form_of_symmetric_skeleton() {
+        // トリ-tert-ブチルメタン. 9 つのメチル基が等価で, 自己同型で枝刈りしないと葉が多い
+        let mut edges = Vec::new();
+        for arm in 0..3 {
+            let quaternary = 1 + 4 * arm;
+            edges.push((0, quaternary, 1));
+            for k in 1..4 {
+                edges.push((quaternary, quaternary + k, 1));
+            }
+        }
+        let a = from_edges(13, &edges);
+        let relabel = |v: usize| (5 * v + 3) % 13;
+        let relabeled: Vec<_> = edges
+            .iter()
+            .map(|&(u, v, order)| (relabel(u), relabel(v), order))
+            .collect();
+        let b = from_edges(13, &relabeled);
+        assert_eq!(a.canonicalize::<13>(), b.canonicalize::<13>());
+        assert_eq!(a.canonicalize::<13>().count_hydrogen(), 28);
+    }
+
+    #[test]
+    fn canonical_form_separates_isomers() {
+        let butane = from_edges(4, &[(0, 1, 1), (1, 2, 1), (2, 3, 1)]);
+        let isobutane = from_edges(4, &[(0, 1, 1), (0, 2, 1), (0, 3, 1)]);
+        let but_1_ene = from_edges(4, &[(0, 1, 2), (1, 2, 1), (2, 3, 1)]);
+        let but_2_ene = from_edges(4, &[(0, 1, 1), (1, 2, 2), (2, 3, 1)]);
+        let forms = [butane, isobutane, but_1_ene, but_2_ene].map(|m| m.canonicalize::<4>());
+        for i in 0..forms.len() {
+            for j in (i + 1)..forms.len() {
+                assert_ne!(forms[i], forms[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn comparison_reports_differences() {
+        let propane = from_edges(3, &[(0, 1, 1), (1, 2, 1)]);
+        let propene = from_edges(3, &[(0, 1, 2), (1, 2, 1)]);
+        let propene_relabeled = from_edges(3, &[(2, 1, 2), (1, 0, 1)]);
+        let cyclopropane = from_edges(3, &[(0, 1, 1), (1, 2, 1), (2, 0, 1)]);
+
+        let a = [propane.clone(), propene, propene_relabeled];
+        let b = [propane, cyclopropane.clone()];
+        let comparison = Comparison::new::<3>(&a, &b);
+        assert_eq!(comparison.duplicates_a, 1);
+        assert_eq!(comparison.duplicates_b, 0);
+        assert_eq!(comparison.only_in_a.len(), 1);
+        assert_eq!(comparison.only_in_b, vec![cyclopropane.canonicalize::<3>()]);
+        assert!(!comparison.is_consistent());
+    }
+
+    #[test]
+    fn latest_chapters_agree() {
+        let a = Chapter::P2Ch07_3.gen_all_hydrocarbons::<6>().unwrap();
+        let b = Chapter::P2Ch07_4.gen_all_hydrocarbons::<6>().unwrap();
+        assert!(Comparison::new::<6>(&a, &b).is_consistent());
+    }
+}
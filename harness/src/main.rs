@@ -0,0 +1,87 @@
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+mod cli;
+
+use std::process::ExitCode;
+
+use cli::{Args, CliError};
+use hydrocarbon_harness::Comparison;
+use hydrocarbon_p2_ch07_4::{Carbon, EnumerationError, SelectStorage};
+
+// 比較結果を表示し, 2つの章が一致したかを返す
+fn run_impl<const N: usize>(args: &Args) -> Result<bool, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let (a, b) = (args.chapter_a, args.chapter_b);
+    let comparison = Comparison::new::<N>(
+        &a.gen_all_hydrocarbons::<N>()?,
+        &b.gen_all_hydrocarbons::<N>()?,
+    );
+
+    println!("===== [C = {N:>2}] =====");
+    for (chapter, count, duplicates) in [
+        (a, comparison.count_a, comparison.duplicates_a),
+        (b, comparison.count_b, comparison.duplicates_b),
+    ] {
+        println!(
+            "{:<10} {count} isomers, {duplicates} duplicates",
+            chapter.name()
+        );
+    }
+    for (chapter, only_in) in [(a, &comparison.only_in_a), (b, &comparison.only_in_b)] {
+        println!("only in {:<10} {}", chapter.name(), only_in.len());
+        if args.show_diff {
+            for molecule in only_in {
+                println!("--- C{}H{} ---", N, molecule.count_hydrogen());
+                println!("{molecule}");
+            }
+        }
+    }
+    Ok(comparison.is_consistent())
+}
+
+// 実行時の炭素数を const generics の run_impl::<N> に振り分ける
+macro_rules! dispatch {
+    ($n:expr, $args:expr; $($c:literal),+) => {
+        match $n {
+            $($c => run_impl::<$c>($args),)+
+            _ => unreachable!("carbon count is validated by the CLI parser"),
+        }
+    };
+}
+
+fn main() -> ExitCode {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut consistent = true;
+    for n in args.carbons.clone() {
+        let result = dispatch!(
+            n, &args;
+            2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16
+        );
+        match result {
+            Ok(ok) => consistent &= ok,
+            Err(e) => {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if consistent {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,7 @@
+mod chapter;
+mod comparison;
+mod molecule;
+
+pub use chapter::Chapter;
+pub use comparison::Comparison;
+pub use molecule::Molecule;
@@ -0,0 +1,98 @@
+use hydrocarbon_p2_ch07_4::{Carbon, EnumerationError, SelectStorage};
+
+use super::molecule::Molecule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chapter {
+    P1Ch04,
+    P2Ch01,
+    P2Ch02,
+    P2Ch03_1,
+    P2Ch03_2,
+    P2Ch03_3,
+    P2Ch04,
+    P2Ch05,
+    P2Ch06,
+    P2Ch07_1,
+    P2Ch07_2,
+    P2Ch07_3,
+    P2Ch07_4,
+}
+
+impl Chapter {
+    pub const ALL: [Chapter; 13] = [
+        Chapter::P1Ch04,
+        Chapter::P2Ch01,
+        Chapter::P2Ch02,
+        Chapter::P2Ch03_1,
+        Chapter::P2Ch03_2,
+        Chapter::P2Ch03_3,
+        Chapter::P2Ch04,
+        Chapter::P2Ch05,
+        Chapter::P2Ch06,
+        Chapter::P2Ch07_1,
+        Chapter::P2Ch07_2,
+        Chapter::P2Ch07_3,
+        Chapter::P2Ch07_4,
+    ];
+
+    /// ディレクトリ名と同じ表記 (例: "p2_ch07-4")
+    pub fn name(self) -> &'static str {
+        match self {
+            Chapter::P1Ch04 => "p1_ch04",
+            Chapter::P2Ch01 => "p2_ch01",
+            Chapter::P2Ch02 => "p2_ch02",
+            Chapter::P2Ch03_1 => "p2_ch03-1",
+            Chapter::P2Ch03_2 => "p2_ch03-2",
+            Chapter::P2Ch03_3 => "p2_ch03-3",
+            Chapter::P2Ch04 => "p2_ch04",
+            Chapter::P2Ch05 => "p2_ch05",
+            Chapter::P2Ch06 => "p2_ch06",
+            Chapter::P2Ch07_1 => "p2_ch07-1",
+            Chapter::P2Ch07_2 => "p2_ch07-2",
+            Chapter::P2Ch07_3 => "p2_ch07-3",
+            Chapter::P2Ch07_4 => "p2_ch07-4",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Chapter> {
+        Chapter::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// その章の gen_all_hydrocarbons で炭素数 N の異性体を列挙する
+    pub fn gen_all_hydrocarbons<const N: usize>(self) -> Result<Vec<Molecule>, EnumerationError>
+    where
+        Carbon<N>: SelectStorage,
+    {
+        macro_rules! convert {
+            ($hydrocarbons:expr) => {
+                $hydrocarbons
+                    .iter()
+                    .map(|h| Molecule::from_fn(N, |i, j| h.element_at(i, j) as u8))
+                    .collect()
+            };
+        }
+
+        let molecules = match self {
+            Chapter::P1Ch04 => convert!(hydrocarbon_p1_ch04::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch01 => convert!(hydrocarbon_p2_ch01::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch02 => convert!(hydrocarbon_p2_ch02::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch03_1 => convert!(hydrocarbon_p2_ch03_1::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch03_2 => convert!(hydrocarbon_p2_ch03_2::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch03_3 => convert!(hydrocarbon_p2_ch03_3::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch04 => convert!(hydrocarbon_p2_ch04::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch05 => convert!(hydrocarbon_p2_ch05::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch06 => convert!(hydrocarbon_p2_ch06::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch07_1 => convert!(hydrocarbon_p2_ch07_1::gen_all_hydrocarbons::<N>()),
+            Chapter::P2Ch07_2 => convert!(hydrocarbon_p2_ch07_2::gen_all_hydrocarbons::<N>(1024)),
+            Chapter::P2Ch07_3 => convert!(hydrocarbon_p2_ch07_3::gen_all_hydrocarbons::<N>(
+                N.saturating_sub(3),
+                1024
+            )),
+            Chapter::P2Ch07_4 => {
+                convert!(hydrocarbon_p2_ch07_4::gen_all_hydrocarbons::<N>()?)
+            }
+        };
+        Ok(molecules)
+    }
+}
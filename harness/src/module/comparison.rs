@@ -0,0 +1,72 @@
+use hydrocarbon_p2_ch07_4::{Carbon, SelectStorage};
+use rustc_hash::FxHashMap;
+
+use super::molecule::Molecule;
+
+/// 2つの列挙結果を正準形の集合として比べた結果
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    pub count_a: usize,
+    pub count_b: usize,
+    /// 同じ列挙結果の中で同型なものが重複して現れた数
+    pub duplicates_a: usize,
+    pub duplicates_b: usize,
+    /// 片方にしか現れない異性体 (正準形, 昇順)
+    pub only_in_a: Vec<Molecule>,
+    pub only_in_b: Vec<Molecule>,
+}
+
+impl Comparison {
+    /// 炭素数 `N` の2つの列挙結果を比べる
+    pub fn new<const N: usize>(a: &[Molecule], b: &[Molecule]) -> Self
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let (set_a, duplicates_a) = canonical_set::<N>(a);
+        let (set_b, duplicates_b) = canonical_set::<N>(b);
+        let mut only_in_a: Vec<_> = set_a
+            .keys()
+            .filter(|m| !set_b.contains_key(*m))
+            .cloned()
+            .collect();
+        let mut only_in_b: Vec<_> = set_b
+            .keys()
+            .filter(|m| !set_a.contains_key(*m))
+            .cloned()
+            .collect();
+        only_in_a.sort_unstable();
+        only_in_b.sort_unstable();
+        Self {
+            count_a: a.len(),
+            count_b: b.len(),
+            duplicates_a,
+            duplicates_b,
+            only_in_a,
+            only_in_b,
+        }
+    }
+
+    /// 重複がなく, 正準形の集合が一致するか
+    pub fn is_consistent(&self) -> bool {
+        self.duplicates_a == 0
+            && self.duplicates_b == 0
+            && self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+    }
+}
+
+fn canonical_set<const N: usize>(molecules: &[Molecule]) -> (FxHashMap<Molecule, usize>, usize)
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut set = FxHashMap::default();
+    let mut duplicates = 0;
+    for molecule in molecules {
+        let count = set.entry(molecule.canonicalize::<N>()).or_insert(0);
+        if *count > 0 {
+            duplicates += 1;
+        }
+        *count += 1;
+    }
+    (set, duplicates)
+}
@@ -0,0 +1,75 @@
+use hydrocarbon_p2_ch07_4::{
+    canonical_order, AdjacencyTwoBitsMatrix, Carbon, SelectStorage, StorageOf,
+};
+
+/// 章ごとの行列表現に依存しない, 炭素骨格の結合次数行列
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Molecule {
+    num_carbons: usize,
+    bonds: Vec<u8>,
+}
+
+impl Molecule {
+    pub fn from_fn(num_carbons: usize, bond_at: impl Fn(usize, usize) -> u8) -> Self {
+        let mut bonds = vec![0; num_carbons * num_carbons];
+        for irow in 0..num_carbons {
+            for icol in 0..num_carbons {
+                bonds[irow * num_carbons + icol] = bond_at(irow, icol);
+            }
+        }
+        Self { num_carbons, bonds }
+    }
+
+    pub fn num_carbons(&self) -> usize {
+        self.num_carbons
+    }
+
+    pub fn bond_at(&self, irow: usize, icol: usize) -> u8 {
+        self.bonds[irow * self.num_carbons + icol]
+    }
+
+    pub fn degree_of(&self, i: usize) -> usize {
+        (0..self.num_carbons)
+            .map(|j| self.bond_at(i, j) as usize)
+            .sum()
+    }
+
+    pub fn count_hydrogen(&self) -> usize {
+        let degrees_sum: usize = (0..self.num_carbons).map(|i| self.degree_of(i)).sum();
+        4 * self.num_carbons - degrees_sum
+    }
+
+    /// 同型な分子が同じ行列になるよう頂点を並べ替える. 順序は p2_ch07-4 の canonical_order で決める.
+    /// 炭素数は `N` に一致する必要がある
+    pub fn canonicalize<const N: usize>(&self) -> Molecule
+    where
+        Carbon<N>: SelectStorage,
+    {
+        assert_eq!(self.num_carbons, N, "number of carbons");
+        let mut hydrocarbon = AdjacencyTwoBitsMatrix::<N, StorageOf<N>>::empty();
+        for irow in 0..N {
+            for icol in (irow + 1)..N {
+                for _ in 0..self.bond_at(irow, icol) {
+                    hydrocarbon.increment_at(irow, icol);
+                }
+            }
+        }
+        let order = canonical_order(&hydrocarbon);
+        Molecule::from_fn(N, |i, j| self.bond_at(order[i], order[j]))
+    }
+}
+
+impl std::fmt::Display for Molecule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for irow in 0..self.num_carbons {
+            for icol in 0..self.num_carbons {
+                write!(f, "{} ", self.bond_at(irow, icol))?;
+            }
+            if irow == self.num_carbons - 1 {
+                break;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
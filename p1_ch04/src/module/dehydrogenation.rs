@@ -35,8 +35,9 @@ fn find_possible_index_pairs<const N: usize>(
 ) -> Vec<(usize, usize)> {
     let mut ables = [false; N];
     let mut pairs = Vec::with_capacity(2 * N);
+    let max_degree = if N == 2 { 3 } else { 4 }; // N = 2 での四重結合を防ぐ
     for irow in 0..N {
-        if hydrocarbon.degree_of(irow) >= 4 {
+        if hydrocarbon.degree_of(irow) >= max_degree {
             continue;
         }
         ables[irow] = true;
@@ -26,6 +26,7 @@ impl<const N: usize> AdjacencyMatrix<N> {
         self.elements[icol][irow] = 0;
     }
 
+    #[allow(clippy::needless_range_loop)]
     fn is_connected(&self) -> bool {
         let mut visited = [false; N];
         visited[0] = true;
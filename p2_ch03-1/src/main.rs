@@ -1,28 +1,9 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-mod module;
-
 use rustc_hash::FxHashMap;
 
-use module::{generate_dehydrogenated, AdjacencyTwoBitsMatrix, SaturatedHydrocarbonIter};
-
-fn gen_all_hydrocarbons<const N: usize>() -> Vec<AdjacencyTwoBitsMatrix<N>> {
-    let mut result = Vec::new();
-    for skeleton in SaturatedHydrocarbonIter::new() {
-        let dehydrogenated = generate_dehydrogenated(skeleton.into());
-        result.extend(dehydrogenated);
-    }
-    result
-}
-
-fn count_hydrogen<const N: usize>(hydrocarbon: &AdjacencyTwoBitsMatrix<N>) -> usize {
-    let mut degrees_sum = 0;
-    for i in 0..N {
-        degrees_sum += hydrocarbon.degree_of(i) as usize;
-    }
-    4 * N - degrees_sum
-}
+use hydrocarbon_p2_ch03_1::{count_hydrogen, gen_all_hydrocarbons};
 
 fn run_impl<const N: usize>() {
     let hydrocarbons = gen_all_hydrocarbons::<N>();
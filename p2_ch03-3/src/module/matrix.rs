@@ -121,7 +121,7 @@ impl<const N: usize> Permutable<N> for AdjacencyTwoBitsMatrix<N> {
 impl<const N: usize> From<AdjacencyBitMatrix<N>> for AdjacencyTwoBitsMatrix<N> {
     fn from(mat: AdjacencyBitMatrix<N>) -> Self {
         let mut rows = [0; N];
-        for (row_2, row_1) in rows.iter_mut().zip(mat.rows.into_iter()) {
+        for (row_2, row_1) in rows.iter_mut().zip(mat.rows) {
             for icol in HotBitIter::from(row_1) {
                 *row_2 |= 1 << (2 * icol);
             }
@@ -0,0 +1,37 @@
+mod module;
+
+pub use module::AdjacencyTwoBitsMatrix;
+
+use module::{generate_dehydrogenated, PermutationStore, SaturatedHydrocarbonIter};
+
+pub fn gen_all_hydrocarbons<const N: usize>() -> Vec<AdjacencyTwoBitsMatrix<N>> {
+    let perm_store = PermutationStore::new();
+    let mut result = Vec::new();
+    for (skeleton, stabilizer) in SaturatedHydrocarbonIter::new(&perm_store) {
+        let dehydrogenated = generate_dehydrogenated(skeleton.into(), &stabilizer);
+        result.extend(dehydrogenated);
+    }
+    result
+}
+
+pub fn count_hydrogen<const N: usize>(hydrocarbon: &AdjacencyTwoBitsMatrix<N>) -> usize {
+    let mut degrees_sum = 0;
+    for i in 0..N {
+        degrees_sum += hydrocarbon.degree_of(i) as usize;
+    }
+    4 * N - degrees_sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<const N: usize>() {
+        let hydrocarbons = gen_all_hydrocarbons::<N>();
+        hydrocarbon_testdata::assert_reference_counts(N, hydrocarbons.iter().map(count_hydrogen));
+    }
+
+    hydrocarbon_testdata::golden_tests! {
+        check => [c2 = 2, c3 = 3, c4 = 4, c5 = 5, c6 = 6, c7 = 7, c8 = 8, c9 = 9, c10 = 10],
+    }
+}
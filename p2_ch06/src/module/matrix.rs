@@ -60,6 +60,7 @@ impl<const N: usize> AdjacencyBitMatrix<N> {
     fn calc_raw_features(&self) -> [u64; N] {
         let mut array_feat = [[0; 3]; N];
         let mut mat = Self::UNIT_MATRIX;
+        #[allow(clippy::needless_range_loop)]
         for step in 0..3 {
             mat = self * &mat;
 
@@ -99,9 +100,10 @@ impl<const N: usize> AdjacencyBitMatrix<N> {
 impl<const N: usize> std::ops::Mul<&[[u16; N]; N]> for &AdjacencyBitMatrix<N> {
     type Output = [[u16; N]; N];
 
+    #[allow(clippy::suspicious_arithmetic_impl)]
     fn mul(self, rhs: &[[u16; N]; N]) -> Self::Output {
         let mut out = [[0; N]; N];
-        for (out_row, self_row) in out.iter_mut().zip(self.rows.into_iter()) {
+        for (out_row, self_row) in out.iter_mut().zip(self.rows) {
             for j in HotBitIter::from(self_row) {
                 for (out_elem, &elem) in out_row.iter_mut().zip(rhs[j].iter()) {
                     *out_elem += elem;
@@ -212,7 +214,7 @@ impl<const N: usize> Permutable<N> for AdjacencyTwoBitsMatrix<N> {
 impl<const N: usize> From<AdjacencyBitMatrix<N>> for AdjacencyTwoBitsMatrix<N> {
     fn from(mat: AdjacencyBitMatrix<N>) -> Self {
         let mut rows = [0; N];
-        for (row_2, row_1) in rows.iter_mut().zip(mat.rows.into_iter()) {
+        for (row_2, row_1) in rows.iter_mut().zip(mat.rows) {
             for icol in HotBitIter::from(row_1) {
                 *row_2 |= 1 << (2 * icol);
             }
@@ -370,7 +372,7 @@ impl<const N: usize> Iterator for SaturatedHydrocarbonIter<'_, N> {
             if self
                 .seen_orbits
                 .get(&feat)
-                .map_or(false, |s| s.contains(&skeleton))
+                .is_some_and(|s| s.contains(&skeleton))
             {
                 continue;
             }
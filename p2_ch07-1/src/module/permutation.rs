@@ -50,8 +50,8 @@ impl<const N: usize> std::ops::Mul for Permutation<N> {
 
     fn mul(self, rhs: Self) -> Self::Output {
         let mut raw = [0; N];
-        for i in 0..N {
-            raw[i] = self.raw[rhs.raw[i]];
+        for (item, &i) in raw.iter_mut().zip(rhs.raw.iter()) {
+            *item = self.raw[i];
         }
         Self::new(raw)
     }
@@ -1,30 +1,9 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-mod module;
-
 use rustc_hash::FxHashMap;
 
-use module::{
-    create_feat2skeletons, dehydrogenate_feat2skeletons, AdjacencyTwoBitsMatrix, PermutationStore,
-};
-
-fn gen_all_hydrocarbons<const N: usize>(
-    fix_digits: usize,
-    max_num_feats: usize,
-) -> Vec<AdjacencyTwoBitsMatrix<N>> {
-    let perm_store = PermutationStore::new();
-    let feat2skeletons = create_feat2skeletons(fix_digits);
-    dehydrogenate_feat2skeletons(feat2skeletons, &perm_store, max_num_feats)
-}
-
-fn count_hydrogen<const N: usize>(hydrocarbon: &AdjacencyTwoBitsMatrix<N>) -> usize {
-    let mut degrees_sum = 0;
-    for i in 0..N {
-        degrees_sum += hydrocarbon.degree_of(i) as usize;
-    }
-    4 * N - degrees_sum
-}
+use hydrocarbon_p2_ch07_3::{count_hydrogen, gen_all_hydrocarbons};
 
 fn run_impl<const N: usize>(fix_digits: usize, max_num_feats: usize) {
     let hydrocarbons = gen_all_hydrocarbons::<N>(fix_digits, max_num_feats);
@@ -0,0 +1,434 @@
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+
+use hydrocarbon_p2_ch07_4::{Formula, MAX_CARBONS, MIN_CARBONS};
+
+// carbons_list! に並べた, 単相化済みの炭素数
+const DISPATCHED_CARBONS: &[usize] = &carbons_list!(carbons_array!());
+
+/// CLI で受け付ける炭素数. ライブラリの範囲のうち, carbons_list! に含まれるもの
+pub const SUPPORTED_CARBONS: RangeInclusive<usize> = {
+    let first = DISPATCHED_CARBONS[0];
+    let last = DISPATCHED_CARBONS[DISPATCHED_CARBONS.len() - 1];
+    let min = if first > MIN_CARBONS {
+        first
+    } else {
+        MIN_CARBONS
+    };
+    let max = if last < MAX_CARBONS {
+        last
+    } else {
+        MAX_CARBONS
+    };
+    min..=max
+};
+
+pub const USAGE: &str = "\
+Usage: hydrocarbon enumerate [OPTIONS]
+
+Options:
+  --carbons <RANGE>        Carbon counts to enumerate, e.g. 8, 4..12 or 4..=12 [default: 2..=10]
+  --fix-digits <DIGITS>    Number of fixed digits used to split the skeleton search
+                           [default: log2(threads) + 4, capped for small N]
+  --max-num-feats <NUM>    Number of features handled by one dehydrogenation job [default: 1024]
+  --threads <NUM>          Number of worker threads [default: available parallelism]
+  --hydrogens <NUM>        Only enumerate isomers with this number of hydrogens
+  --formula <FORMULA>      Enumerate isomers of a molecular formula with heteroatoms
+                           (C, N, O, S, F, Cl, Br, I), e.g. C4H10O. Overrides
+                           --carbons and --hydrogens
+  --open-valences <NUM>    Enumerate radicals and ions with NUM open valences (unpaired
+                           electrons or formal charges on carbon), e.g. 1 for C4H9
+                           radicals or C5H11+ cations. Open valences are not told
+                           apart, so radicals and ions share one count and mixed
+                           species such as radical cations are not distinguished.
+                           Only species counts are reported; --aromatic, --stereo
+                           and --chirality are ignored
+  --acyclic                Only enumerate open-chain isomers (no rings)
+  --min-ring-size <SIZE>   Exclude isomers containing rings smaller than SIZE
+  --max-ring-size <SIZE>   Exclude isomers containing rings larger than SIZE
+  --max-rings <NUM>        Only enumerate isomers with at most NUM rings
+  --exact-rings <NUM>      Only enumerate isomers with exactly NUM rings
+  --plausible              Skip strained unsaturation in small rings (triple bonds,
+                           trans double bonds, anti-Bredt double bonds, allenes)
+  --no-cumulated           Skip cumulated double bonds (allenes, cumulenes) and report
+                           how many isomers were excluded
+  --collapse-resonance     Count Kekule structures of the same aromatic system once
+  --aromatic               Also count isomers with a Hueckel-aromatic ring
+  --substitute <NUM>       Also count positional isomers of replacing NUM hydrogens
+                           with a substituent X, e.g. 2 for dichloro derivatives
+  --stereo                 Also count stereoisomers (E/Z double bonds and tetrahedral
+                           centers, enantiomers counted separately)
+  --chirality              Also count isomers that are achiral, chiral, or have meso
+                           forms
+  --format <FORMAT>        Output format: table, csv, smiles (one canonical SMILES per
+                           isomer), sdf (one Molfile record with 2D coordinates per
+                           isomer), sparse6 (one multigraph per isomer, bond orders as
+                           edge multiplicities), or graph6 (one line per distinct carbon
+                           skeleton, for comparison with nauty's geng). Counting
+                           options are ignored except for table and csv [default: table]
+  --explicit-hydrogens     Write hydrogens as atoms in sdf output
+  -h, --help               Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Csv,
+    Smiles,
+    Sdf,
+    Sparse6,
+    Graph6,
+}
+
+impl Format {
+    /// 個数ではなく, 構造を1つずつ書き出す形式か
+    pub fn writes_structures(self) -> bool {
+        !matches!(self, Self::Table | Self::Csv)
+    }
+}
+
+impl FromStr for Format {
+    type Err = CliError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Self::Table),
+            "csv" => Ok(Self::Csv),
+            "smiles" => Ok(Self::Smiles),
+            "sdf" => Ok(Self::Sdf),
+            "sparse6" => Ok(Self::Sparse6),
+            "graph6" => Ok(Self::Graph6),
+            _ => Err(CliError::InvalidValue("--format", s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub carbons: RangeInclusive<usize>,
+    pub fix_digits: Option<usize>,
+    pub max_num_feats: usize,
+    pub num_threads: Option<usize>,
+    pub num_hydrogens: Option<usize>,
+    pub formula: Option<Formula>,
+    pub open_valences: usize,
+    pub acyclic: bool,
+    pub min_ring_size: Option<usize>,
+    pub max_ring_size: Option<usize>,
+    pub max_rings: Option<usize>,
+    pub exact_rings: Option<usize>,
+    pub plausible: bool,
+    pub no_cumulated: bool,
+    pub collapse_resonance: bool,
+    pub aromatic: bool,
+    pub substitute: Option<usize>,
+    pub stereo: bool,
+    pub chirality: bool,
+    pub format: Format,
+    pub explicit_hydrogens: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            carbons: 2..=10,
+            fix_digits: None,
+            max_num_feats: 1024,
+            num_threads: None,
+            num_hydrogens: None,
+            formula: None,
+            open_valences: 0,
+            acyclic: false,
+            min_ring_size: None,
+            max_ring_size: None,
+            max_rings: None,
+            exact_rings: None,
+            plausible: false,
+            no_cumulated: false,
+            collapse_resonance: false,
+            aromatic: false,
+            substitute: None,
+            stereo: false,
+            chirality: false,
+            format: Format::Table,
+            explicit_hydrogens: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    Help,
+    MissingCommand,
+    UnknownCommand(String),
+    UnknownOption(String),
+    MissingValue(&'static str),
+    InvalidValue(&'static str, String),
+    CarbonsOutOfRange(RangeInclusive<usize>),
+    ConflictingOptions(&'static str, &'static str),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Help => write!(f, "{USAGE}"),
+            Self::MissingCommand => write!(f, "no command given\n\n{USAGE}"),
+            Self::UnknownCommand(cmd) => write!(f, "unknown command `{cmd}`\n\n{USAGE}"),
+            Self::UnknownOption(opt) => write!(f, "unknown option `{opt}`\n\n{USAGE}"),
+            Self::MissingValue(opt) => write!(f, "option `{opt}` requires a value"),
+            Self::InvalidValue(opt, value) => {
+                write!(f, "invalid value `{value}` for option `{opt}`")
+            }
+            Self::CarbonsOutOfRange(range) => write!(
+                f,
+                "carbon counts {}..={} are out of range: supported range is {}..={}",
+                range.start(),
+                range.end(),
+                SUPPORTED_CARBONS.start(),
+                SUPPORTED_CARBONS.end()
+            ),
+            Self::ConflictingOptions(a, b) => {
+                write!(f, "options `{a}` and `{b}` cannot be used together")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, CliError> {
+    match args.next().as_deref() {
+        Some("enumerate") => (),
+        Some("-h" | "--help") => return Err(CliError::Help),
+        Some(cmd) => return Err(CliError::UnknownCommand(cmd.to_string())),
+        None => return Err(CliError::MissingCommand),
+    }
+
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        // `--option=value` と `--option value` の両方を受け付ける
+        let (opt, inline_value) = match arg.split_once('=') {
+            Some((opt, value)) if opt.starts_with("--") => {
+                (opt.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let mut value = |name: &'static str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or(CliError::MissingValue(name))
+        };
+        match opt.as_str() {
+            "--carbons" => parsed.carbons = parse_carbons(&value("--carbons")?)?,
+            "--fix-digits" => {
+                parsed.fix_digits = Some(parse_number("--fix-digits", &value("--fix-digits")?)?)
+            }
+            "--max-num-feats" => {
+                parsed.max_num_feats = parse_number("--max-num-feats", &value("--max-num-feats")?)?;
+                if parsed.max_num_feats == 0 {
+                    return Err(CliError::InvalidValue("--max-num-feats", "0".to_string()));
+                }
+            }
+            "--threads" => {
+                let num_threads = parse_number("--threads", &value("--threads")?)?;
+                if num_threads == 0 {
+                    return Err(CliError::InvalidValue("--threads", "0".to_string()));
+                }
+                parsed.num_threads = Some(num_threads);
+            }
+            "--hydrogens" => {
+                parsed.num_hydrogens = Some(parse_number("--hydrogens", &value("--hydrogens")?)?)
+            }
+            "--formula" => parsed.formula = Some(parse_formula(&value("--formula")?)?),
+            "--open-valences" => {
+                parsed.open_valences = parse_number("--open-valences", &value("--open-valences")?)?
+            }
+            "--acyclic" => parsed.acyclic = true,
+            "--min-ring-size" => {
+                parsed.min_ring_size =
+                    Some(parse_number("--min-ring-size", &value("--min-ring-size")?)?)
+            }
+            "--max-ring-size" => {
+                parsed.max_ring_size =
+                    Some(parse_number("--max-ring-size", &value("--max-ring-size")?)?)
+            }
+            "--max-rings" => {
+                parsed.max_rings = Some(parse_number("--max-rings", &value("--max-rings")?)?)
+            }
+            "--exact-rings" => {
+                parsed.exact_rings = Some(parse_number("--exact-rings", &value("--exact-rings")?)?)
+            }
+            "--plausible" => parsed.plausible = true,
+            "--no-cumulated" => parsed.no_cumulated = true,
+            "--collapse-resonance" => parsed.collapse_resonance = true,
+            "--aromatic" => parsed.aromatic = true,
+            "--substitute" => {
+                parsed.substitute = Some(parse_number("--substitute", &value("--substitute")?)?)
+            }
+            "--stereo" => parsed.stereo = true,
+            "--chirality" => parsed.chirality = true,
+            "--format" => parsed.format = value("--format")?.parse()?,
+            "--explicit-hydrogens" => parsed.explicit_hydrogens = true,
+            "-h" | "--help" => return Err(CliError::Help),
+            _ => return Err(CliError::UnknownOption(opt)),
+        }
+    }
+    // 構造を書き出す形式は炭化水素の列挙にのみ対応する
+    let structure_format = match parsed.format {
+        Format::Smiles => Some("--format smiles"),
+        Format::Sdf => Some("--format sdf"),
+        Format::Sparse6 => Some("--format sparse6"),
+        Format::Graph6 => Some("--format graph6"),
+        Format::Table | Format::Csv => None,
+    };
+    if let Some(format) = structure_format {
+        if parsed.formula.is_some() {
+            return Err(CliError::ConflictingOptions(format, "--formula"));
+        }
+        if parsed.open_valences > 0 {
+            return Err(CliError::ConflictingOptions(format, "--open-valences"));
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_number(name: &'static str, s: &str) -> Result<usize, CliError> {
+    s.parse()
+        .map_err(|_| CliError::InvalidValue(name, s.to_string()))
+}
+
+fn parse_formula(s: &str) -> Result<Formula, CliError> {
+    let formula: Formula = s
+        .parse()
+        .map_err(|_| CliError::InvalidValue("--formula", s.to_string()))?;
+    let num_atoms = formula.num_heavy_atoms();
+    if !SUPPORTED_CARBONS.contains(&num_atoms) {
+        return Err(CliError::CarbonsOutOfRange(num_atoms..=num_atoms));
+    }
+    Ok(formula)
+}
+
+fn parse_carbons(s: &str) -> Result<RangeInclusive<usize>, CliError> {
+    let invalid = || CliError::InvalidValue("--carbons", s.to_string());
+    let range = if let Some((start, end)) = s.split_once("..=") {
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = end.parse().map_err(|_| invalid())?;
+        start..=end
+    } else if let Some((start, end)) = s.split_once("..") {
+        let start = start.parse().map_err(|_| invalid())?;
+        let end: usize = end.parse().map_err(|_| invalid())?;
+        start..=end.checked_sub(1).ok_or_else(invalid)?
+    } else {
+        let n = s.parse().map_err(|_| invalid())?;
+        n..=n
+    };
+
+    if range.is_empty() {
+        return Err(invalid());
+    }
+    if range.start() < SUPPORTED_CARBONS.start() || range.end() > SUPPORTED_CARBONS.end() {
+        return Err(CliError::CarbonsOutOfRange(range));
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, CliError> {
+        parse_args(
+            std::iter::once("enumerate")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    fn carbons(value: &str) -> Result<RangeInclusive<usize>, CliError> {
+        parse(&["--carbons", value]).map(|args| args.carbons)
+    }
+
+    #[test]
+    fn carbons_range_syntax() {
+        assert_eq!(carbons("6"), Ok(6..=6));
+        assert_eq!(carbons("2..8"), Ok(2..=7));
+        assert_eq!(carbons("2..=8"), Ok(2..=8));
+        assert_eq!(
+            parse(&["--carbons=4..=12"]).map(|args| args.carbons),
+            Ok(4..=12)
+        );
+        assert_eq!(parse(&[]).map(|args| args.carbons), Ok(2..=10));
+        for value in ["", "x", "8..2", "6..6", "2..", "..=8", "-1"] {
+            assert_eq!(
+                carbons(value),
+                Err(CliError::InvalidValue("--carbons", value.to_string())),
+                "{value:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn carbons_out_of_range() {
+        assert_eq!(carbons("1"), Err(CliError::CarbonsOutOfRange(1..=1)));
+        assert_eq!(carbons("0..=4"), Err(CliError::CarbonsOutOfRange(0..=4)));
+        let too_many = SUPPORTED_CARBONS.end() + 1;
+        assert_eq!(
+            carbons(&format!("2..={too_many}")),
+            Err(CliError::CarbonsOutOfRange(2..=too_many))
+        );
+        assert_eq!(
+            carbons(&SUPPORTED_CARBONS.end().to_string()),
+            Ok(*SUPPORTED_CARBONS.end()..=*SUPPORTED_CARBONS.end())
+        );
+        // ライブラリが扱う炭素数は全て CLI でも受け付ける
+        assert_eq!(SUPPORTED_CARBONS, MIN_CARBONS..=MAX_CARBONS);
+    }
+
+    #[test]
+    fn missing_values() {
+        for opt in [
+            "--carbons",
+            "--hydrogens",
+            "--formula",
+            "--format",
+            "--threads",
+        ] {
+            assert_eq!(parse(&[opt]).err(), Some(CliError::MissingValue(opt)));
+        }
+        assert_eq!(
+            parse_args(std::iter::empty()).err(),
+            Some(CliError::MissingCommand)
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(
+            parse(&["--threads", "0"]).err(),
+            Some(CliError::InvalidValue("--threads", "0".to_string()))
+        );
+        assert_eq!(
+            parse(&["--format", "xml"]).err(),
+            Some(CliError::InvalidValue("--format", "xml".to_string()))
+        );
+        assert_eq!(
+            parse(&["--bogus"]).err(),
+            Some(CliError::UnknownOption("--bogus".to_string()))
+        );
+    }
+
+    #[test]
+    fn conflicting_options() {
+        assert_eq!(
+            parse(&["--format", "smiles", "--formula", "C2H6O"]).err(),
+            Some(CliError::ConflictingOptions("--format smiles", "--formula"))
+        );
+        assert_eq!(
+            parse(&["--open-valences", "1", "--format=graph6"]).err(),
+            Some(CliError::ConflictingOptions(
+                "--format graph6",
+                "--open-valences"
+            ))
+        );
+        assert!(parse(&["--format", "csv", "--formula", "C2H6O"]).is_ok());
+    }
+}
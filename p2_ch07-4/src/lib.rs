@@ -0,0 +1,208 @@
+mod module;
+
+pub use module::{
+    aromatic_atoms, canonical_order, count_chirality, count_columns, count_isomers,
+    count_isomers_with_excluded, count_species, count_stereoisomers, count_substitution_isomers,
+    enumerate, enumerate_formula, enumerate_into, enumerate_skeletons, enumerate_species,
+    enumerate_stereoisomers, enumerate_substitution_isomers, enumerate_with_symmetry,
+    gen_all_hydrocarbons, hydrogen_directions, is_aromatic, layout_2d, to_smiles, write_molfile,
+    AdjacencyBitMatrix, AdjacencyTwoBitsMatrix, Bits, Carbon, Chirality, ChiralityCounts,
+    ColumnCounts, Columns, CountingSink, Element, EnumerationConfig, EnumerationError, Filter,
+    FilterSink, Formula, IsomerSink, Molecule, PlausibilityRules, SdfSink, SelectStorage,
+    SmilesSink, Sparse6Sink, Species, StereoAnalysis, StereoUnit, Storage, Storage16, Storage32,
+    Storage64, StorageOf, Substitution, SubstitutionIsomers, Symmetry, WriterSink, BOND_LENGTH,
+    MAX_CARBONS, MAX_STEREO_ACTIONS, MIN_CARBONS,
+};
+
+#[cfg(test)]
+mod tests {
+    use hydrocarbon_testdata::{assert_reference_counts, reference_counts};
+
+    use super::*;
+
+    // 列挙した異性体と, 保持せずに数える経路の両方を参照表と比べる
+    fn check<const N: usize>()
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let hydrocarbons = gen_all_hydrocarbons::<N>().unwrap();
+        assert_reference_counts(N, hydrocarbons.iter().map(|h| h.count_hydrogen()));
+        let counts = count_isomers::<N>(EnumerationConfig::default()).unwrap();
+        assert_eq!(counts, reference_counts(N), "C = {N}");
+    }
+
+    hydrocarbon_testdata::golden_tests! {
+        check => [c2 = 2, c3 = 3, c4 = 4, c5 = 5, c6 = 6, c7 = 7, c8 = 8, c9 = 9, c10 = 10],
+    }
+
+    // 水素数を指定した列挙が参照表の該当する行と一致することを確かめる
+    fn assert_formula_counts<const N: usize>()
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let reference = reference_counts(N);
+        for num_h in (0..=(N + 1)).map(|x| 2 * x) {
+            let config = EnumerationConfig {
+                num_hydrogens: Some(num_h),
+                ..Default::default()
+            };
+            let counts = count_isomers::<N>(config).unwrap();
+            assert_eq!(
+                counts.iter().sum::<usize>(),
+                reference[num_h],
+                "C{N}H{num_h}"
+            );
+        }
+    }
+
+    #[test]
+    fn formula() {
+        assert_formula_counts::<5>();
+        assert_formula_counts::<6>();
+        assert_formula_counts::<7>();
+    }
+
+    // 規則のもとで列挙される, 水素数 num_h の異性体の正準 SMILES
+    fn plausible_smiles<const N: usize>(
+        num_h: usize,
+        plausibility: PlausibilityRules,
+    ) -> std::collections::HashSet<String>
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let config = EnumerationConfig {
+            num_hydrogens: Some(num_h),
+            plausibility,
+            ..Default::default()
+        };
+        enumerate::<N>(config)
+            .unwrap()
+            .iter()
+            .map(to_smiles)
+            .collect()
+    }
+
+    // 規則を有効にすると除かれ, 無効なら列挙されることを確かめる
+    fn assert_rejected<const N: usize>(smiles: &str, rules: PlausibilityRules)
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let hydrocarbon: AdjacencyTwoBitsMatrix<N, StorageOf<N>> = smiles.parse().unwrap();
+        let num_h = hydrocarbon.count_hydrogen();
+        let canonical = to_smiles(&hydrocarbon);
+        let all = plausible_smiles::<N>(num_h, PlausibilityRules::default());
+        assert!(all.contains(&canonical), "{smiles}");
+        assert!(
+            !plausible_smiles::<N>(num_h, rules).contains(&canonical),
+            "{smiles}"
+        );
+        assert!(
+            !plausible_smiles::<N>(num_h, PlausibilityRules::all()).contains(&canonical),
+            "{smiles}"
+        );
+    }
+
+    fn assert_allowed<const N: usize>(smiles: &str)
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let hydrocarbon: AdjacencyTwoBitsMatrix<N, StorageOf<N>> = smiles.parse().unwrap();
+        let canonical = to_smiles(&hydrocarbon);
+        let plausible =
+            plausible_smiles::<N>(hydrocarbon.count_hydrogen(), PlausibilityRules::all());
+        assert!(plausible.contains(&canonical), "{smiles}");
+    }
+
+    #[test]
+    fn plausibility() {
+        let only = |set: fn(&mut PlausibilityRules)| {
+            let mut rules = PlausibilityRules::default();
+            set(&mut rules);
+            rules
+        };
+        // ビシクロ[2.2.1]ヘプタ-1-エン: 橋頭位の二重結合は Bredt 則で除く
+        assert_rejected::<7>("C1C=C2CCC1C2", only(|r| r.bredt = true));
+        // 2-ノルボルネン: 二重結合は五員環の中でシス配置にできる
+        assert_allowed::<7>("C1=CC2CCC1C2");
+        // ビシクロ[3.3.1]ノナ-1-エン: トランス配置になる環は八員環で, trans-シクロオクテンと同じく許す
+        assert_allowed::<9>("C12=CCCC(C2)CCC1");
+        assert_allowed::<8>("C1=CCCCCCC1");
+        // シクロヘキシン
+        assert_rejected::<6>(
+            "C1#CCCCC1",
+            only(|r| r.no_triple_bonds_in_small_rings = true),
+        );
+        // 1,2-シクロヘプタジエン
+        assert_rejected::<7>("C1=C=CCCCC1", only(|r| r.no_allenes_in_small_rings = true));
+    }
+
+    fn count_formula<const N: usize>(formula: &str) -> usize
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let formula: Formula = formula.parse().unwrap();
+        let molecules = enumerate_formula::<N>(&formula, Default::default()).unwrap();
+        assert!(molecules
+            .iter()
+            .all(|m| m.count_hydrogen() == formula.num_hydrogens()));
+        molecules.len()
+    }
+
+    #[test]
+    fn formula_parsing() {
+        for s in ["C4H10O", "C3H6Cl2", "CH4O", "C2H7N", "Cl2"] {
+            assert_eq!(s.parse::<Formula>().unwrap().to_string(), s);
+        }
+        let formula: Formula = "H6C2OClC".parse().unwrap();
+        assert_eq!(formula.to_string(), "C3H6ClO");
+        assert_eq!(formula.count(Element::C), 3);
+        assert_eq!(formula.num_heavy_atoms(), 5);
+        for s in [
+            "",
+            "H2",
+            "C2X",
+            "c2h6",
+            "C2H",
+            "C99999999999999999999",
+            "H18446744073709551615H1C",
+            "C18446744073709551615C",
+            "C9223372036854775807O",
+        ] {
+            let formula = s.parse::<Formula>();
+            if s == "C2H" {
+                assert_eq!(formula.unwrap().degree_of_unsaturation(), None);
+            } else {
+                assert!(formula.is_err(), "{s}");
+            }
+        }
+    }
+
+    #[test]
+    fn heteroatoms() {
+        assert_eq!(count_formula::<2>("CH4O"), 1);
+        assert_eq!(count_formula::<3>("C2H6O"), 2);
+        assert_eq!(count_formula::<3>("C2H4O"), 3);
+        assert_eq!(count_formula::<3>("C2H7N"), 2);
+        assert_eq!(count_formula::<4>("C2H4Cl2"), 2);
+        assert_eq!(count_formula::<4>("C2H2Cl2"), 2);
+        assert_eq!(count_formula::<4>("C3H8O"), 3);
+        assert_eq!(count_formula::<4>("C3H9N"), 4);
+        assert_eq!(count_formula::<5>("C4H10O"), 7);
+        assert_eq!(count_formula::<5>("C3H6Cl2"), 4);
+
+        // 炭素のみの分子式は炭化水素の列挙と一致する
+        for num_h in (0..=14).step_by(2) {
+            let formula = format!("C6H{num_h}");
+            assert_eq!(count_formula::<6>(&formula), reference_counts(6)[num_h]);
+        }
+
+        let formula: Formula = "C4H10O".parse().unwrap();
+        assert_eq!(
+            enumerate_formula::<4>(&formula, Default::default()).unwrap_err(),
+            EnumerationError::HeavyAtomCountMismatch {
+                formula: 5,
+                vertices: 4
+            }
+        );
+    }
+}
@@ -1,54 +1,319 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-mod module;
+// CLI で扱う炭素数の一覧. 炭素数ごとに単相化するため, 一覧はここにだけ書く.
+// ライブラリの MIN_CARBONS..=MAX_CARBONS を全て並べる. 炭素数ごとに列挙の全体を単相化するので,
+// 一覧の長さにほぼ比例してコンパイルが遅くなる
+macro_rules! carbons_list {
+    ($callback:ident!($($args:tt)*)) => {
+        $callback!($($args)*; 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+            17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+            33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48,
+            49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64)
+    };
+}
+
+macro_rules! carbons_array {
+    (; $($c:literal),+) => {
+        [$($c),+]
+    };
+}
+
+// 実行時の炭素数 $n を const $N に束縛して $body を評価する
+macro_rules! dispatch {
+    ($n:expr, $N:ident => $body:expr) => {
+        carbons_list!(dispatch_impl!($n, $N, $body))
+    };
+}
 
-use rustc_hash::FxHashMap;
+macro_rules! dispatch_impl {
+    ($n:expr, $N:ident, $body:expr; $($c:literal),+) => {
+        match $n {
+            $($c => {
+                const $N: usize = $c;
+                $body
+            })+
+            _ => unreachable!("carbon count is validated by the CLI parser"),
+        }
+    };
+}
+
+mod cli;
+
+use std::{io::Write, process::ExitCode};
 
-use module::{
-    create_feat2skeletons, dehydrogenate_feat2skeletons, AdjacencyTwoBitsMatrix, PermutationStore,
+use cli::{Args, CliError, Format};
+use hydrocarbon_p2_ch07_4::{
+    count_columns, count_species, enumerate_formula, enumerate_into, enumerate_skeletons, Carbon,
+    ColumnCounts, Columns, EnumerationConfig, EnumerationError, Formula, PlausibilityRules,
+    SdfSink, SelectStorage, SmilesSink, Sparse6Sink,
 };
 
-fn gen_all_hydrocarbons<const N: usize>(
-    fix_digits: usize,
-    max_num_feats: usize,
-) -> Vec<AdjacencyTwoBitsMatrix<N>> {
-    let perm_store = PermutationStore::new();
-    let feat2skeletons = create_feat2skeletons(fix_digits);
-    dehydrogenate_feat2skeletons(feat2skeletons, &perm_store, max_num_feats)
+fn plausibility_rules(args: &Args) -> PlausibilityRules {
+    let mut plausibility = if args.plausible {
+        PlausibilityRules::all()
+    } else {
+        PlausibilityRules::default()
+    };
+    plausibility.no_cumulated_double_bonds = args.no_cumulated;
+    plausibility
 }
 
-fn count_hydrogen<const N: usize>(hydrocarbon: &AdjacencyTwoBitsMatrix<N>) -> usize {
-    let mut degrees_sum = 0;
-    for i in 0..N {
-        degrees_sum += hydrocarbon.degree_of(i) as usize;
+fn config<const N: usize>(args: &Args, plausibility: PlausibilityRules) -> EnumerationConfig<N>
+where
+    Carbon<N>: SelectStorage,
+{
+    EnumerationConfig {
+        fix_digits: args
+            .fix_digits
+            .unwrap_or_else(|| EnumerationConfig::<N>::default_fix_digits(args.num_threads)),
+        max_num_feats: args.max_num_feats,
+        num_threads: args.num_threads,
+        num_hydrogens: args.num_hydrogens,
+        acyclic: args.acyclic,
+        min_ring_size: args.min_ring_size,
+        max_ring_size: args.max_ring_size,
+        max_rings: args.max_rings,
+        exact_rings: args.exact_rings,
+        plausibility,
+        collapse_resonance: args.collapse_resonance,
+        ..Default::default()
     }
-    4 * N - degrees_sum
 }
 
-fn run_impl<const N: usize>(fix_digits: usize, max_num_feats: usize) {
-    let hydrocarbons = gen_all_hydrocarbons::<N>(fix_digits, max_num_feats);
-    let mut counts = FxHashMap::default();
-    for hydrocarbon in hydrocarbons {
-        let num_h = count_hydrogen(&hydrocarbon);
-        *counts.entry(num_h).or_insert(0) += 1;
+fn run_impl<const N: usize>(args: &Args) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    // 追加の列は全て1回の列挙で求める
+    let columns = Columns {
+        excluded: args.no_cumulated,
+        aromatic: args.aromatic,
+        substituted: args.substitute,
+        stereoisomers: args.stereo,
+        chirality: args.chirality,
+    };
+    let ColumnCounts {
+        counts,
+        excluded,
+        aromatic,
+        substituted,
+        stereoisomers: stereo,
+        chirality,
+    } = count_columns::<N>(config(args, plausibility_rules(args)), columns)?;
+    let nums_h: Vec<_> = match args.num_hydrogens {
+        Some(num_h) => vec![num_h],
+        None => (0..=(N + 1)).map(|x| 2 * x).collect(),
+    };
+    let get = |counts: &[usize], num_h: usize| counts.get(num_h).copied().unwrap_or(0);
+    match args.format {
+        Format::Table => {
+            println!("===== [C = {N:>2}] =====");
+            print!("#H: #Hydrocarbons");
+            if excluded.is_some() {
+                print!(" (#Excluded)");
+            }
+            if aromatic.is_some() {
+                print!(", #Aromatic");
+            }
+            if let Some(k) = args.substitute {
+                print!(", #Substituted({k})");
+            }
+            if stereo.is_some() {
+                print!(", #Stereoisomers");
+            }
+            if chirality.is_some() {
+                print!(", #Achiral/#Chiral/#Meso");
+            }
+            println!();
+            for &num_h in &nums_h {
+                print!("{:>2}: {}", num_h, get(&counts, num_h));
+                if let Some(excluded) = &excluded {
+                    print!(" ({})", get(excluded, num_h));
+                }
+                if let Some(aromatic) = &aromatic {
+                    print!(", {}", get(aromatic, num_h));
+                }
+                if let Some(substituted) = &substituted {
+                    print!(", {}", get(substituted, num_h));
+                }
+                if let Some(stereo) = &stereo {
+                    print!(", {}", get(stereo, num_h));
+                }
+                if let Some(chirality) = &chirality {
+                    let c = chirality.get(num_h).copied().unwrap_or_default();
+                    print!(", {}/{}/{}", c.achiral, c.chiral, c.meso);
+                }
+                println!();
+            }
+        }
+        Format::Csv => {
+            for &num_h in &nums_h {
+                print!("{},{},{}", N, num_h, get(&counts, num_h));
+                for column in [&excluded, &aromatic, &substituted, &stereo]
+                    .into_iter()
+                    .flatten()
+                {
+                    print!(",{}", get(column, num_h));
+                }
+                if let Some(chirality) = &chirality {
+                    let c = chirality.get(num_h).copied().unwrap_or_default();
+                    print!(",{},{},{}", c.achiral, c.chiral, c.meso);
+                }
+                println!();
+            }
+        }
+        _ => unreachable!("structures are written by run_structures"),
     }
-    println!("===== [C = {N:>2}] =====");
-    println!("#H: #Hydrocarbons");
-    for num_h in (0..=(N + 1)).map(|x| 2 * x) {
-        println!("{:>2}: {}", num_h, counts.get(&num_h).unwrap_or(&0));
+    Ok(())
+}
+
+// 炭化水素を列挙し, 構造を SMILES, SDF, sparse6 のいずれかで出力する.
+// graph6 では炭化水素の代わりに炭素骨格を出力する
+fn run_structures<const N: usize>(args: &Args) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let config = config(args, plausibility_rules(args));
+    let stdout = std::io::stdout().lock();
+    match args.format {
+        Format::Smiles => enumerate_into::<N>(config, SmilesSink::new(stdout)),
+        Format::Sdf => enumerate_into::<N>(config, SdfSink::new(stdout, args.explicit_hydrogens)),
+        Format::Sparse6 => enumerate_into::<N>(config, Sparse6Sink::new(stdout)),
+        Format::Graph6 => {
+            let mut stdout = stdout;
+            for skeleton in enumerate_skeletons::<N>(config)? {
+                writeln!(stdout, "{}", skeleton.to_graph6())?;
+            }
+            Ok(())
+        }
+        Format::Table | Format::Csv => unreachable!("counts are written by run_impl"),
     }
 }
 
-macro_rules! run {
-    ($($n:expr),+; fix_digits=$d:expr; max_num_feats=$mnf:expr) => {
-        $(run_impl::<$n>($d, $mnf);)+
-    };
-    ($($n:expr),+; max_num_feats=$mnf:expr) => {
-        $(run_impl::<$n>(if $n < 3 { 0 } else { $n - 3 }, $mnf);)+
+// 開いた原子価を持つ化学種の数を, 水素数ごとに出力する
+fn run_species<const N: usize>(args: &Args) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let counts = count_species::<N>(args.open_valences, config(args, plausibility_rules(args)))?;
+    // 水素数の偶奇は開いた原子価の数で決まる
+    let max_h = (2 * N + 2).saturating_sub(args.open_valences);
+    let nums_h: Vec<_> = match args.num_hydrogens {
+        Some(num_h) => vec![num_h],
+        None => (max_h % 2..=max_h).step_by(2).collect(),
     };
+    let get = |num_h: usize| counts.get(num_h).copied().unwrap_or(0);
+    match args.format {
+        Format::Table => {
+            println!("===== [C = {N:>2}] =====");
+            println!("#H: #Species");
+            for &num_h in &nums_h {
+                println!("{:>2}: {}", num_h, get(num_h));
+            }
+        }
+        Format::Csv => {
+            for &num_h in &nums_h {
+                println!("{},{},{}", N, num_h, get(num_h));
+            }
+        }
+        _ => unreachable!("rejected by the CLI parser"),
+    }
+    Ok(())
+}
+
+// 水素以外の原子の数が N の分子式について, 構造異性体の数を出力する
+fn run_formula<const N: usize>(args: &Args, formula: &Formula) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let molecules = enumerate_formula::<N>(formula, config(args, plausibility_rules(args)))?;
+    match args.format {
+        Format::Table => {
+            println!("===== [{formula}] =====");
+            println!("#Isomers: {}", molecules.len());
+        }
+        Format::Csv => println!("{formula},{}", molecules.len()),
+        _ => unreachable!("rejected by the CLI parser"),
+    }
+    Ok(())
 }
 
-fn main() {
-    run!(2, 3, 4, 5, 6, 7, 8, 9, 10; max_num_feats=1024);
+fn main() -> ExitCode {
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(CliError::Help) => {
+            println!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(formula) = &args.formula {
+        if args.format == Format::Csv {
+            println!("formula,count");
+        }
+        let result = dispatch!(formula.num_heavy_atoms(), N => run_formula::<N>(&args, formula));
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if args.open_valences > 0 {
+        if args.format == Format::Csv {
+            println!("carbons,hydrogens,count");
+        }
+        for n in args.carbons.clone() {
+            let result = dispatch!(n, N => run_species::<N>(&args));
+            if let Err(e) = result {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if args.format.writes_structures() {
+        for n in args.carbons.clone() {
+            let result = dispatch!(n, N => run_structures::<N>(&args));
+            if let Err(e) = result {
+                eprintln!("error: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if args.format == Format::Csv {
+        print!("carbons,hydrogens,count");
+        if args.no_cumulated {
+            print!(",excluded");
+        }
+        if args.aromatic {
+            print!(",aromatic");
+        }
+        if args.substitute.is_some() {
+            print!(",substituted");
+        }
+        if args.stereo {
+            print!(",stereoisomers");
+        }
+        if args.chirality {
+            print!(",achiral,chiral,meso");
+        }
+        println!();
+    }
+    for n in args.carbons.clone() {
+        let result = dispatch!(n, N => run_impl::<N>(&args));
+        if let Err(e) = result {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
 }
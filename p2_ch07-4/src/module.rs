@@ -1,9 +1,49 @@
+mod aromaticity;
+mod canon;
 mod dehydrogenation;
+mod element;
+mod enumeration;
+mod error;
+mod graph6;
 mod hot_bit_iter;
+mod layout;
 mod matrix;
 mod parallel;
 mod permutation;
+mod plausibility;
+mod sdf;
+mod sink;
+mod smiles;
+mod species;
+mod stereo;
+mod storage;
+mod symmetry;
+#[cfg(test)]
+pub(crate) mod testing;
 
-pub use matrix::AdjacencyTwoBitsMatrix;
-pub use parallel::{create_feat2skeletons, dehydrogenate_feat2skeletons};
-pub use permutation::PermutationStore;
+pub use aromaticity::{aromatic_atoms, is_aromatic};
+pub use canon::canonical_order;
+pub use element::{Element, Formula, Molecule};
+pub use enumeration::{
+    count_chirality, count_columns, count_isomers, count_isomers_with_excluded, count_species,
+    count_stereoisomers, count_substitution_isomers, enumerate, enumerate_formula, enumerate_into,
+    enumerate_skeletons, enumerate_species, enumerate_stereoisomers,
+    enumerate_substitution_isomers, enumerate_with_symmetry, gen_all_hydrocarbons, ColumnCounts,
+    Columns, EnumerationConfig, SubstitutionIsomers, MAX_CARBONS, MIN_CARBONS,
+};
+pub use error::EnumerationError;
+pub use layout::{hydrogen_directions, layout_2d, BOND_LENGTH};
+pub use matrix::{AdjacencyBitMatrix, AdjacencyTwoBitsMatrix};
+pub use parallel::Filter;
+pub use plausibility::PlausibilityRules;
+pub use sdf::write_molfile;
+pub use sink::{
+    CountingSink, FilterSink, IsomerSink, SdfSink, SmilesSink, Sparse6Sink, WriterSink,
+};
+pub use smiles::to_smiles;
+pub use species::Species;
+pub use stereo::{Chirality, ChiralityCounts, StereoAnalysis, StereoUnit, MAX_STEREO_ACTIONS};
+pub use storage::{
+    Bits, Carbon, SelectStorage, Storage, Storage16, Storage32, Storage64, StorageOf,
+};
+pub use symmetry::{Substitution, Symmetry};
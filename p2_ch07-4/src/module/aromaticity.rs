@@ -0,0 +1,209 @@
+use std::array;
+
+use rustc_hash::FxHashSet;
+
+use super::{
+    matrix::AdjacencyTwoBitsMatrix,
+    permutation::{calc_orbit_stabilizer, Permutable, Permutation},
+    storage::Storage,
+};
+
+/// Hückel 則に従う芳香環に含まれる原子.
+/// 各炭素が π 電子を1つずつ出すとみなし, 環の全ての原子が環の原子と二重結合で結ばれ,
+/// 環の大きさが 4n + 2 のものを芳香環とする. 環の平面性は考えない
+pub fn aromatic_atoms<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+) -> [bool; N] {
+    // 二重結合をちょうど1本持ち, 他に多重結合を持たない原子の, 二重結合の相手
+    let partners = array::from_fn(|u| {
+        if hydrocarbon.count_multiple_bonds_of(u) != 1 {
+            return None;
+        }
+        (0..N).find(|&v| hydrocarbon.element_at(u, v) == 2)
+    });
+    let mut search = CycleSearch {
+        hydrocarbon,
+        partners,
+        path: Vec::with_capacity(N),
+        on_path: [false; N],
+        aromatic: [false; N],
+    };
+    for start in (0..N).filter(|&u| partners[u].is_some()) {
+        search.path.push(start);
+        search.on_path[start] = true;
+        search.extend_path();
+        search.path.pop();
+        search.on_path[start] = false;
+    }
+    search.aromatic
+}
+
+/// 芳香環を1つ以上含むか
+pub fn is_aromatic<const N: usize, S: Storage>(hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>) -> bool {
+    aromatic_atoms(hydrocarbon).contains(&true)
+}
+
+/// 芳香環の Kekulé 構造が異なるだけの炭化水素を, 最初の1つを残して除く.
+/// `hydrocarbons` は骨格 `skeleton` の脱水素化の結果で, `stabilizer` は骨格の固定部分群の生成元
+pub fn collapse_resonance<const N: usize, S: Storage>(
+    skeleton: &AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+    hydrocarbons: &mut Vec<AdjacencyTwoBitsMatrix<N, S>>,
+) {
+    let mut seen_orbits = FxHashSet::default();
+    hydrocarbons.retain(|hydrocarbon| {
+        let aromatic = aromatic_atoms(hydrocarbon);
+        if !aromatic.contains(&true) {
+            return true;
+        }
+        let key = ResonanceKey::new(skeleton, hydrocarbon, aromatic);
+        if seen_orbits.contains(&key) {
+            return false;
+        }
+        let (orbit, _) = calc_orbit_stabilizer(key, stabilizer, 0);
+        seen_orbits.extend(orbit);
+        true
+    });
+}
+
+/// 芳香環の Kekulé 構造を区別しない, 炭化水素の自己同型群の生成元.
+/// `stabilizer` は骨格 `skeleton` の固定部分群の生成元
+pub fn resonance_automorphisms<const N: usize, S: Storage>(
+    skeleton: &AdjacencyTwoBitsMatrix<N, S>,
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+) -> Vec<Permutation<N>> {
+    let aromatic = aromatic_atoms(hydrocarbon);
+    if aromatic.contains(&true) {
+        let key = ResonanceKey::new(skeleton, hydrocarbon, aromatic);
+        calc_orbit_stabilizer(key, stabilizer, 0).1
+    } else {
+        calc_orbit_stabilizer(*hydrocarbon, stabilizer, 0).1
+    }
+}
+
+// 始点より番号の大きい候補原子だけを辿り, 始点に戻る単純な閉路を全て調べる
+struct CycleSearch<'a, const N: usize, S: Storage> {
+    hydrocarbon: &'a AdjacencyTwoBitsMatrix<N, S>,
+    partners: [Option<usize>; N],
+    path: Vec<usize>,
+    on_path: [bool; N],
+    aromatic: [bool; N],
+}
+
+impl<const N: usize, S: Storage> CycleSearch<'_, N, S> {
+    fn extend_path(&mut self) {
+        let start = self.path[0];
+        let last = self.path[self.path.len() - 1];
+        for next in start..N {
+            if self.hydrocarbon.element_at(last, next) == 0 || self.partners[next].is_none() {
+                continue;
+            }
+            if next == start {
+                if self.is_aromatic_cycle() {
+                    for &u in &self.path {
+                        self.aromatic[u] = true;
+                    }
+                }
+            } else if !self.on_path[next] {
+                self.path.push(next);
+                self.on_path[next] = true;
+                self.extend_path();
+                self.path.pop();
+                self.on_path[next] = false;
+            }
+        }
+    }
+
+    fn is_aromatic_cycle(&self) -> bool {
+        self.path.len() > 2
+            && self.path.len() % 4 == 2
+            && self
+                .path
+                .iter()
+                .all(|&u| self.partners[u].is_some_and(|v| self.on_path[v]))
+    }
+}
+
+// 芳香環の原子どうしの結合を全て単結合に戻した行列と, 芳香環の原子の組.
+// Kekulé 構造が異なるだけの炭化水素は同じ値になる
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct ResonanceKey<const N: usize, S: Storage> {
+    bonds: AdjacencyTwoBitsMatrix<N, S>,
+    aromatic: [bool; N],
+}
+
+impl<const N: usize, S: Storage> ResonanceKey<N, S> {
+    fn new(
+        skeleton: &AdjacencyTwoBitsMatrix<N, S>,
+        hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+        aromatic: [bool; N],
+    ) -> Self {
+        let mut bonds = *skeleton;
+        for u in 0..N {
+            for v in (u + 1..N).filter(|&v| !(aromatic[u] && aromatic[v])) {
+                for _ in 1..hydrocarbon.element_at(u, v) {
+                    bonds.increment_at(u, v);
+                }
+            }
+        }
+        Self { bonds, aromatic }
+    }
+}
+
+impl<const N: usize, S: Storage> Permutable<N> for ResonanceKey<N, S> {
+    fn permute_by(&self, perm: &[usize; N]) -> Self {
+        Self {
+            bonds: self.bonds.permute_by(perm),
+            aromatic: self.aromatic.permute_by(perm),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::enumeration::{count_isomers, EnumerationConfig},
+        *,
+    };
+
+    #[test]
+    fn aromaticity() {
+        let count_aromatic = |collapse_resonance| {
+            let config = EnumerationConfig {
+                num_hydrogens: Some(10),
+                filters: vec![Box::new(is_aromatic)],
+                collapse_resonance,
+                ..Default::default()
+            };
+            count_isomers::<8>(config).unwrap()[10]
+        };
+        // エチルベンゼンとキシレン3種. o-キシレンのみ2つの Kekulé 構造が対称性で移り合わない
+        assert_eq!(count_aromatic(false), 5);
+        assert_eq!(count_aromatic(true), 4);
+
+        let count_all = |collapse_resonance| {
+            let config = EnumerationConfig {
+                collapse_resonance,
+                ..Default::default()
+            };
+            count_isomers::<8>(config).unwrap()[10]
+        };
+        assert_eq!(count_all(false) - count_all(true), 1);
+
+        // 全ての原子が芳香環に含まれる二環式の C10H8 は, 縮合二環 (ナフタレン, アズレン,
+        // ビシクロ[6.2.0], ビシクロ[7.1.0]) の Kekulé 構造 7 つで, まとめると 4 つになる
+        let count_fully_aromatic = |collapse_resonance| {
+            let config = EnumerationConfig {
+                num_hydrogens: Some(8),
+                exact_rings: Some(2),
+                filters: vec![Box::new(|h| aromatic_atoms(h).iter().all(|&a| a))],
+                collapse_resonance,
+                ..Default::default()
+            };
+            count_isomers::<10>(config).unwrap()[8]
+        };
+        assert_eq!(count_fully_aromatic(false), 7);
+        assert_eq!(count_fully_aromatic(true), 4);
+    }
+}
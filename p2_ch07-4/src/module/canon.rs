@@ -0,0 +1,135 @@
+use super::{matrix::AdjacencyTwoBitsMatrix, storage::Storage};
+
+/// 原子の正準な順序. `order[rank]` が順位 `rank` の原子で,
+/// 同型な炭化水素はこの順に並べ替えると同じ行列になる.
+/// 分割の細分化と個別化による探索で, 隣接行列の上三角が辞書順で最小となる順序を選ぶ
+pub fn canonical_order<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+) -> [usize; N] {
+    let mut search = CanonicalSearch {
+        hydrocarbon,
+        prefix: Vec::new(),
+        best: None,
+        automorphisms: Vec::new(),
+    };
+    let partition = search.refine(vec![(0..N).collect()]);
+    search.search(partition);
+    search.best.expect("search reaches at least one leaf").0
+}
+
+// 順序付きの分割. 各セルは原子の集合で, セルの並びが順位を決める
+type Partition = Vec<Vec<usize>>;
+
+struct CanonicalSearch<'a, const N: usize, S: Storage> {
+    hydrocarbon: &'a AdjacencyTwoBitsMatrix<N, S>,
+    // 探索中に個別化した原子
+    prefix: Vec<usize>,
+    // これまでで最小の証明書を与える順序と, その証明書
+    best: Option<([usize; N], Vec<u32>)>,
+    // 証明書が一致した葉どうしから得た自己同型
+    automorphisms: Vec<[usize; N]>,
+}
+
+impl<const N: usize, S: Storage> CanonicalSearch<'_, N, S> {
+    fn search(&mut self, partition: Partition) {
+        let Some(icell) = partition.iter().position(|cell| cell.len() > 1) else {
+            self.visit_leaf(&partition);
+            return;
+        };
+        let mut explored: Vec<usize> = Vec::new();
+        for &v in &partition[icell] {
+            // 個別化した原子を固定する自己同型で移り合う原子は, 同じ結果を与える
+            let orbit = self.orbit_fixing_prefix(v);
+            if explored.iter().any(|u| orbit.contains(u)) {
+                continue;
+            }
+            explored.push(v);
+
+            let mut individualized = partition.clone();
+            let rest: Vec<_> = partition[icell]
+                .iter()
+                .copied()
+                .filter(|&u| u != v)
+                .collect();
+            individualized.splice(icell..=icell, [vec![v], rest]);
+            self.prefix.push(v);
+            let refined = self.refine(individualized);
+            self.search(refined);
+            self.prefix.pop();
+        }
+    }
+
+    fn visit_leaf(&mut self, partition: &Partition) {
+        let mut order = [0; N];
+        for (rank, cell) in partition.iter().enumerate() {
+            order[rank] = cell[0];
+        }
+        let certificate: Vec<_> = (0..N)
+            .flat_map(|i| (i + 1..N).map(move |j| (i, j)))
+            .map(|(i, j)| self.hydrocarbon.element_at(order[i], order[j]))
+            .collect();
+        match &self.best {
+            Some((best_order, best_certificate)) if *best_certificate == certificate => {
+                let mut automorphism = [0; N];
+                for rank in 0..N {
+                    automorphism[best_order[rank]] = order[rank];
+                }
+                self.automorphisms.push(automorphism);
+            }
+            Some((_, best_certificate)) if *best_certificate < certificate => (),
+            _ => self.best = Some((order, certificate)),
+        }
+    }
+
+    fn orbit_fixing_prefix(&self, v: usize) -> Vec<usize> {
+        let automorphisms: Vec<_> = self
+            .automorphisms
+            .iter()
+            .filter(|g| self.prefix.iter().all(|&u| g[u] == u))
+            .collect();
+        let mut orbit = vec![v];
+        let mut i = 0;
+        while i < orbit.len() {
+            for g in &automorphisms {
+                let image = g[orbit[i]];
+                if !orbit.contains(&image) {
+                    orbit.push(image);
+                }
+            }
+            i += 1;
+        }
+        orbit
+    }
+
+    // 各原子を, 隣接原子のセルと結合次数の組で分け直すことを, 変化がなくなるまで繰り返す
+    fn refine(&self, mut partition: Partition) -> Partition {
+        loop {
+            let mut cell_of = [0; N];
+            for (icell, cell) in partition.iter().enumerate() {
+                for &u in cell {
+                    cell_of[u] = icell;
+                }
+            }
+            let signature = |u: usize| {
+                let mut neighbors: Vec<_> = (0..N)
+                    .filter(|&v| self.hydrocarbon.element_at(u, v) != 0)
+                    .map(|v| (cell_of[v], self.hydrocarbon.element_at(u, v)))
+                    .collect();
+                neighbors.sort_unstable();
+                neighbors
+            };
+            let mut refined = Vec::with_capacity(N);
+            for cell in &partition {
+                let mut signed: Vec<_> = cell.iter().map(|&u| (signature(u), u)).collect();
+                signed.sort();
+                for chunk in signed.chunk_by(|a, b| a.0 == b.0) {
+                    refined.push(chunk.iter().map(|&(_, u)| u).collect());
+                }
+            }
+            if refined.len() == partition.len() {
+                return refined;
+            }
+            partition = refined;
+        }
+    }
+}
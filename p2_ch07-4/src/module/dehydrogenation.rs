@@ -4,53 +4,150 @@ use super::{
     hot_bit_iter::HotBitIter,
     matrix::AdjacencyTwoBitsMatrix,
     permutation::{calc_orbit, Permutation},
+    plausibility::{BondLimits, PlausibilityRules},
+    storage::{Bits, Storage},
 };
 
-pub fn generate_dehydrogenated<const N: usize>(
-    skeleton: AdjacencyTwoBitsMatrix<N>,
+/// 原子価 `valences` の原子について, 脱水素化で許される結合次数の和の上限
+pub fn max_degrees<const N: usize>(valences: [u32; N]) -> [u32; N] {
+    let cap = if N == 2 { 3 } else { 4 }; // N = 2 でのオーバーフロー防止
+    valences.map(|valence| valence.min(cap))
+}
+
+pub fn generate_dehydrogenated<const N: usize, S: Storage>(
+    skeleton: AdjacencyTwoBitsMatrix<N, S>,
     stabilizer: &[Permutation<N>],
-) -> Vec<AdjacencyTwoBitsMatrix<N>> {
+    rules: &PlausibilityRules,
+    max_degrees: &[u32; N],
+) -> Vec<AdjacencyTwoBitsMatrix<N, S>> {
     let mut result = vec![skeleton];
+    dehydrogenate_by_level(
+        skeleton,
+        stabilizer,
+        rules,
+        max_degrees,
+        usize::MAX,
+        |_, hydrocarbons| {
+            result.extend_from_slice(hydrocarbons);
+        },
+    );
+    result
+}
+
+/// 骨格にちょうど `depth` 個の結合を追加した炭化水素のみを生成する
+pub fn generate_dehydrogenated_at<const N: usize, S: Storage>(
+    skeleton: AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+    rules: &PlausibilityRules,
+    max_degrees: &[u32; N],
+    depth: usize,
+) -> Vec<AdjacencyTwoBitsMatrix<N, S>> {
+    if depth == 0 {
+        return vec![skeleton];
+    }
+    let mut result = Vec::new();
+    dehydrogenate_by_level(
+        skeleton,
+        stabilizer,
+        rules,
+        max_degrees,
+        depth,
+        |level, hydrocarbons| {
+            if level == depth {
+                result.extend_from_slice(hydrocarbons);
+            }
+        },
+    );
+    result
+}
+
+/// `generate_dehydrogenated` と同じ探索を `max_depth` 段まで行い, 段ごとの炭化水素の個数のみを返す.
+/// 返り値の添字は骨格に追加した結合の数に等しい.
+/// `is_excluded` を満たす炭化水素は1つ目でなく2つ目の返り値に数える
+pub fn count_dehydrogenated<const N: usize, S: Storage>(
+    skeleton: AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+    rules: &PlausibilityRules,
+    max_degrees: &[u32; N],
+    max_depth: usize,
+    is_excluded: impl Fn(&AdjacencyTwoBitsMatrix<N, S>) -> bool,
+) -> (Vec<usize>, Vec<usize>) {
+    let mut counts = vec![1];
+    let mut excluded = vec![0];
+    dehydrogenate_by_level(
+        skeleton,
+        stabilizer,
+        rules,
+        max_degrees,
+        max_depth,
+        |_, hydrocarbons| {
+            let num_excluded = hydrocarbons.iter().filter(|h| is_excluded(h)).count();
+            counts.push(hydrocarbons.len() - num_excluded);
+            excluded.push(num_excluded);
+        },
+    );
+    (counts, excluded)
+}
+
+// 骨格に結合を1つずつ追加する幅優先探索を `max_depth` 段まで行い,
+// 空でない各段を追加した結合の数とともに `on_level` に渡す.
+// `rules` に反する結合の追加は, その先の探索ごと打ち切る.
+// 各原子の結合次数の和は `max_degrees` を超えない
+fn dehydrogenate_by_level<const N: usize, S: Storage>(
+    skeleton: AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+    rules: &PlausibilityRules,
+    max_degrees: &[u32; N],
+    max_depth: usize,
+    mut on_level: impl FnMut(usize, &[AdjacencyTwoBitsMatrix<N, S>]),
+) {
+    let limits = BondLimits::new(&skeleton, rules);
     let mut queue = vec![skeleton];
     let mut seen_orbits = FxHashSet::default();
 
-    while !queue.is_empty() {
+    let mut depth = 0;
+    while !queue.is_empty() && depth < max_depth {
+        depth += 1;
         let mut next_queue = Vec::new();
         for hydrocarbon in queue {
-            let index_pairs = find_possible_index_pairs(&hydrocarbon);
+            let index_pairs = find_possible_index_pairs(&hydrocarbon, max_degrees, limits.as_ref());
             for (irow, icol) in index_pairs {
                 let mut dehydrogenated = hydrocarbon;
                 dehydrogenated.increment_at(irow, icol);
                 if seen_orbits.contains(&dehydrogenated) {
                     continue;
                 }
-                result.push(dehydrogenated);
                 next_queue.push(dehydrogenated);
 
                 let orbit = calc_orbit(dehydrogenated, stabilizer);
                 seen_orbits.extend(orbit);
             }
         }
+        if !next_queue.is_empty() {
+            on_level(depth, &next_queue);
+        }
         queue = next_queue;
         seen_orbits.clear();
     }
-    result
 }
 
-fn find_possible_index_pairs<const N: usize>(
-    hydrocarbon: &AdjacencyTwoBitsMatrix<N>,
+fn find_possible_index_pairs<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+    max_degrees: &[u32; N],
+    limits: Option<&BondLimits<N, S>>,
 ) -> Vec<(usize, usize)> {
-    let mut ables = 0u16;
+    let mut ables = S::Row::ZERO;
     let mut pairs = Vec::with_capacity(2 * N);
-    let max_degree = if N == 2 { 3 } else { 4 }; // N = 2 でのオーバーフロー防止
-    for irow in 0..N {
+    for (irow, &max_degree) in max_degrees.iter().enumerate() {
         if hydrocarbon.degree_of(irow) >= max_degree {
             continue;
         }
-        ables |= 1 << irow;
+        ables |= S::Row::ONE << irow;
 
         for icol in HotBitIter::from(ables) {
-            if hydrocarbon.element_at(irow, icol) != 0 {
+            if hydrocarbon.element_at(irow, icol) != 0
+                && limits.is_none_or(|l| l.allows_increment(hydrocarbon, irow, icol))
+            {
                 pairs.push((irow, icol));
             }
         }
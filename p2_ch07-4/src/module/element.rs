@@ -0,0 +1,232 @@
+use std::{fmt, str::FromStr};
+
+use super::{
+    error::EnumerationError,
+    matrix::AdjacencyTwoBitsMatrix,
+    permutation::{assign_labels, Permutation},
+    storage::Storage,
+};
+
+/// 水素以外の原子の元素. 原子価は最も低いものに固定する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Element {
+    C,
+    N,
+    O,
+    S,
+    F,
+    Cl,
+    Br,
+    I,
+}
+
+impl Element {
+    pub const ALL: [Self; 8] = [
+        Self::C,
+        Self::N,
+        Self::O,
+        Self::S,
+        Self::F,
+        Self::Cl,
+        Self::Br,
+        Self::I,
+    ];
+
+    pub fn valence(self) -> u32 {
+        match self {
+            Self::C => 4,
+            Self::N => 3,
+            Self::O | Self::S => 2,
+            Self::F | Self::Cl | Self::Br | Self::I => 1,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::C => "C",
+            Self::N => "N",
+            Self::O => "O",
+            Self::S => "S",
+            Self::F => "F",
+            Self::Cl => "Cl",
+            Self::Br => "Br",
+            Self::I => "I",
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// 分子式. 水素以外の原子は元素ごとの個数で持つ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formula {
+    counts: [usize; Element::ALL.len()],
+    num_hydrogens: usize,
+}
+
+impl Formula {
+    pub fn count(&self, element: Element) -> usize {
+        self.counts[element as usize]
+    }
+
+    pub fn num_hydrogens(&self) -> usize {
+        self.num_hydrogens
+    }
+
+    /// 水素以外の原子の数. 列挙では骨格の頂点数 `N` に対応する
+    pub fn num_heavy_atoms(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    // 元素の順に並べた, 骨格の頂点に割り当てる元素の多重集合
+    fn elements<const N: usize>(&self) -> Option<[Element; N]> {
+        if self.num_heavy_atoms() != N {
+            return None;
+        }
+        let mut elements = [Element::C; N];
+        let all = Element::ALL
+            .iter()
+            .flat_map(|&e| std::iter::repeat_n(e, self.count(e)));
+        for (item, element) in elements.iter_mut().zip(all) {
+            *item = element;
+        }
+        Some(elements)
+    }
+
+    /// 環と多重結合の数の和 (不飽和度). 水素数の偶奇が合わない場合や負になる場合は `None`
+    pub fn degree_of_unsaturation(&self) -> Option<usize> {
+        let valence_sum: usize = Element::ALL
+            .iter()
+            .map(|&e| e.valence() as usize * self.count(e))
+            .sum();
+        // 全て単結合の木なら水素は Σ原子価 - 2(原子数 - 1) 個
+        let saturated = (valence_sum + 2).checked_sub(2 * self.num_heavy_atoms())?;
+        let diff = saturated.checked_sub(self.num_hydrogens)?;
+        (diff % 2 == 0).then_some(diff / 2)
+    }
+}
+
+impl FromStr for Formula {
+    type Err = EnumerationError;
+
+    /// `C4H10O` や `C3H6Cl2` のような分子式を読む. 同じ元素が複数回現れてもよい
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || EnumerationError::InvalidFormula {
+            formula: s.to_string(),
+        };
+        let mut counts = [0; Element::ALL.len()];
+        let mut num_hydrogens: usize = 0;
+        let mut rest = s;
+        while !rest.is_empty() {
+            let symbol_len = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| !c.is_ascii_lowercase())
+                .map_or(rest.len(), |(i, _)| i);
+            let (symbol, tail) = rest.split_at(symbol_len);
+            let digits_len = tail
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(tail.len());
+            let (digits, tail) = tail.split_at(digits_len);
+            let count = match digits {
+                "" => 1,
+                _ => digits.parse().map_err(|_| invalid())?,
+            };
+            let total = match symbol {
+                "H" => &mut num_hydrogens,
+                _ => {
+                    let element = Element::ALL
+                        .into_iter()
+                        .find(|e| e.symbol() == symbol)
+                        .ok_or_else(invalid)?;
+                    &mut counts[element as usize]
+                }
+            };
+            *total = total.checked_add(count).ok_or_else(invalid)?;
+            rest = tail;
+        }
+        if counts.iter().all(|&c| c == 0) {
+            return Err(invalid());
+        }
+        // 原子価の和 (+ 2) が収まれば, 原子数や不飽和度の計算もあふれない
+        Element::ALL
+            .iter()
+            .try_fold(2usize, |acc, &e| {
+                acc.checked_add((e.valence() as usize).checked_mul(counts[e as usize])?)
+            })
+            .ok_or_else(invalid)?;
+        Ok(Self {
+            counts,
+            num_hydrogens,
+        })
+    }
+}
+
+impl fmt::Display for Formula {
+    /// Hill 式の順 (C, H, 残りはアルファベット順) で書く
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut symbols: Vec<_> = Element::ALL
+            .iter()
+            .map(|&e| (e.symbol(), self.count(e)))
+            .chain([("H", self.num_hydrogens)])
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        let has_carbon = self.count(Element::C) > 0;
+        symbols.sort_by_key(|&(symbol, _)| match symbol {
+            "C" if has_carbon => (0, symbol),
+            "H" if has_carbon => (1, symbol),
+            _ => (2, symbol),
+        });
+        for (symbol, count) in symbols {
+            match count {
+                1 => write!(f, "{symbol}")?,
+                _ => write!(f, "{symbol}{count}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 各頂点に元素を割り当てた分子
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Molecule<const N: usize, S: Storage> {
+    pub elements: [Element; N],
+    pub bonds: AdjacencyTwoBitsMatrix<N, S>,
+}
+
+impl<const N: usize, S: Storage> Molecule<N, S> {
+    pub fn count_hydrogen(&self) -> usize {
+        let valence_sum: u32 = self.elements.iter().map(|e| e.valence()).sum();
+        let degree_sum: u32 = (0..N).map(|i| self.bonds.degree_of(i)).sum();
+        (valence_sum - degree_sum) as usize
+    }
+}
+
+impl<const N: usize, S: Storage> fmt::Display for Molecule<N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for element in &self.elements {
+            write!(f, "{element} ")?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.bonds)
+    }
+}
+
+/// 骨格の頂点に分子式の元素を割り当てる方法を, 骨格の対称性で重複を除いて全て返す.
+/// 各頂点の結合の数は元素の原子価を超えない. 併せて返す置換は, 割り当てを保つ固定部分群の生成元
+pub fn assign_elements<const N: usize, S: Storage>(
+    skeleton: &AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+    formula: &Formula,
+) -> Vec<([Element; N], Vec<Permutation<N>>)> {
+    let Some(elements) = formula.elements::<N>() else {
+        return Vec::new();
+    };
+    assign_labels(elements, stabilizer, |i, element: Element| {
+        skeleton.degree_of(i) <= element.valence()
+    })
+}
@@ -0,0 +1,815 @@
+use rustc_hash::FxHashMap;
+
+use super::{
+    aromaticity::is_aromatic,
+    element::{Formula, Molecule},
+    error::EnumerationError,
+    matrix::{AdjacencyBitMatrix, AdjacencyTwoBitsMatrix, Features, SkeletonConstraints},
+    parallel::{
+        analyze_feat2skeletons, count_feat2skeletons, create_feat2skeletons,
+        deduplicate_feat2skeletons, default_num_threads, dehydrogenate_colored_feat2skeletons,
+        dehydrogenate_feat2skeletons, dehydrogenate_open_feat2skeletons, DehydrogenationOptions,
+        Filter,
+    },
+    permutation::{Permutation, PermutationStore},
+    plausibility::{has_cumulated_double_bonds, PlausibilityRules},
+    sink::IsomerSink,
+    species::Species,
+    stereo::{ChiralityCounts, StereoAnalysis},
+    storage::{Carbon, SelectStorage, StorageOf},
+    symmetry::{Substitution, Symmetry},
+};
+
+pub const MIN_CARBONS: usize = 2;
+pub const MAX_CARBONS: usize = 64;
+
+// 骨格の探索は 2^fix_digits 個に分割され, その添字は u32 で数え上げる
+const MAX_FIX_DIGITS: usize = u32::BITS as usize - 1;
+
+// fix_digits の既定値で, スレッド数の桁に上乗せする桁数. 1スレッドあたり 2^4 個の探索を割り当て,
+// 分割した探索の重さの偏りをならす
+const EXTRA_FIX_DIGITS: usize = 4;
+
+/// 炭化水素の列挙に関する設定
+pub struct EnumerationConfig<const N: usize>
+where
+    Carbon<N>: SelectStorage,
+{
+    /// 骨格の探索を分割する際に固定する桁数
+    pub fix_digits: usize,
+    /// 脱水素化の1ジョブあたりに担当する特徴量の数
+    pub max_num_feats: usize,
+    /// 使用するスレッド数. `None` の場合は `std::thread::available_parallelism` に従う
+    pub num_threads: Option<usize>,
+    /// 全てが `true` を返した炭化水素のみを結果に含める
+    pub filters: Vec<Box<Filter<N>>>,
+    /// 指定した場合, 水素数がこの値に一致する炭化水素 (分子式 C_N H_m) のみを列挙する
+    pub num_hydrogens: Option<usize>,
+    /// `true` の場合, 環を持たない鎖状の炭化水素のみを列挙する
+    pub acyclic: bool,
+    /// 指定した場合, この大きさ未満の環を含む炭化水素を除く
+    pub min_ring_size: Option<usize>,
+    /// 指定した場合, この大きさを超える環を含む炭化水素を除く.
+    /// 環の大きさは, 環に含まれる各結合を通る最短の環で測る
+    pub max_ring_size: Option<usize>,
+    /// 指定した場合, 環の数 (骨格の辺の数 - N + 1) がこの値以下の炭化水素のみを列挙する
+    pub max_rings: Option<usize>,
+    /// 指定した場合, 環の数がちょうどこの値の炭化水素のみを列挙する
+    pub exact_rings: Option<usize>,
+    /// 脱水素化で不飽和結合を置く際に従う, 化学的な妥当性の規則
+    pub plausibility: PlausibilityRules,
+    /// `true` の場合, 芳香環の Kekulé 構造が異なるだけの炭化水素を1つとして数える.
+    /// 元素を割り当てた分子の列挙には適用しない
+    pub collapse_resonance: bool,
+}
+
+impl<const N: usize> Default for EnumerationConfig<N>
+where
+    Carbon<N>: SelectStorage,
+{
+    fn default() -> Self {
+        Self {
+            fix_digits: Self::default_fix_digits(None),
+            max_num_feats: 1024,
+            num_threads: None,
+            filters: Vec::new(),
+            num_hydrogens: None,
+            acyclic: false,
+            min_ring_size: None,
+            max_ring_size: None,
+            max_rings: None,
+            exact_rings: None,
+            plausibility: PlausibilityRules::default(),
+            collapse_resonance: false,
+        }
+    }
+}
+
+impl<const N: usize> EnumerationConfig<N>
+where
+    Carbon<N>: SelectStorage,
+{
+    /// `num_threads` 本のスレッドで使う fix_digits の既定値.
+    /// スレッド数の2進の桁数に定数を足したもので, 炭素数 `N` で扱える上限に収める
+    pub fn default_fix_digits(num_threads: Option<usize>) -> usize {
+        let num_threads = num_threads.unwrap_or_else(default_num_threads);
+        let digits = num_threads.next_power_of_two().trailing_zeros() as usize + EXTRA_FIX_DIGITS;
+        digits.min(Self::max_fix_digits())
+    }
+
+    // N * (N - 1) / 2 - 2 を超える桁は RedundantSaturatedHydrocarbonIter で扱えない
+    fn max_fix_digits() -> usize {
+        (N * (N - 1) / 2).saturating_sub(2).min(MAX_FIX_DIGITS)
+    }
+
+    /// 設定値が炭素数 `N` に対して妥当か検証する
+    pub fn validate(&self) -> Result<(), EnumerationError> {
+        if !(MIN_CARBONS..=MAX_CARBONS).contains(&N) {
+            return Err(EnumerationError::UnsupportedCarbons { carbons: N });
+        }
+        let max_fix_digits = Self::max_fix_digits();
+        if self.fix_digits > max_fix_digits {
+            return Err(EnumerationError::FixDigitsTooLarge {
+                fix_digits: self.fix_digits,
+                max: max_fix_digits,
+            });
+        }
+        if self.max_num_feats == 0 {
+            return Err(EnumerationError::ZeroMaxNumFeats);
+        }
+        if self.num_threads == Some(0) {
+            return Err(EnumerationError::ZeroThreads);
+        }
+        if let (Some(min), Some(max)) = (self.min_ring_size, self.max_ring_size)
+            && min > max
+        {
+            return Err(EnumerationError::InvalidRingSizes { min, max });
+        }
+        if let Some(exact) = self.exact_rings
+            && let Some(max) = self.max_rings()
+            && exact > max
+        {
+            return Err(EnumerationError::InvalidRingCounts { exact, max });
+        }
+        Ok(())
+    }
+
+    fn dehydrogenation_options(&self) -> DehydrogenationOptions<'_, N, StorageOf<N>> {
+        DehydrogenationOptions {
+            filters: &self.filters,
+            num_hydrogens: self.num_hydrogens,
+            plausibility: self.plausibility,
+            collapse_resonance: self.collapse_resonance,
+        }
+    }
+
+    // acyclic と max_rings のうち厳しい方
+    fn max_rings(&self) -> Option<usize> {
+        if self.acyclic {
+            Some(0)
+        } else {
+            self.max_rings
+        }
+    }
+
+    fn skeleton_constraints(&self) -> SkeletonConstraints {
+        let mut constraints = SkeletonConstraints::default();
+        if let Some(max) = self.max_rings() {
+            constraints.max_rings = max;
+        }
+        if let Some(exact) = self.exact_rings {
+            constraints.max_rings = constraints.max_rings.min(exact);
+            constraints.min_rings = exact;
+        }
+        if let Some(num_h) = self.num_hydrogens {
+            // 環が1つ増えると骨格の水素は2つ減るので, 水素数から環の数の上限が決まる
+            let max = (2 * N + 2).saturating_sub(num_h) / 2;
+            constraints.max_rings = constraints.max_rings.min(max);
+        }
+        if let Some(min) = self.min_ring_size {
+            constraints.min_ring_size = min;
+        }
+        if let Some(max) = self.max_ring_size {
+            constraints.max_ring_size = max;
+        }
+        constraints
+    }
+
+    fn create_feat2skeletons(
+        &self,
+    ) -> Result<FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, StorageOf<N>>>>, EnumerationError>
+    {
+        let mut feat2skeletons = create_feat2skeletons(
+            self.fix_digits,
+            self.skeleton_constraints(),
+            self.num_threads,
+        )?;
+        if let Some(num_h) = self.num_hydrogens {
+            // 特徴量が等しい骨格は辺の数, すなわち水素数も等しいので, 先頭の骨格で判定できる.
+            // 結合を追加すると水素数は減る一方なので, 骨格の時点で足りなければ到達できない
+            feat2skeletons.retain(|_, skeletons| {
+                AdjacencyTwoBitsMatrix::from(skeletons[0]).count_hydrogen() >= num_h
+            });
+        }
+        Ok(feat2skeletons)
+    }
+}
+
+/// 炭素数 `N` の炭化水素の構造異性体を全て列挙する
+pub fn enumerate<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<AdjacencyTwoBitsMatrix<N, StorageOf<N>>>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut hydrocarbons = Vec::new();
+    enumerate_into(config, &mut hydrocarbons)?;
+    Ok(hydrocarbons)
+}
+
+/// 既定の設定で炭素数 `N` の炭化水素の構造異性体を全て列挙する.
+/// 他の章と同じ名前の入口として用意している
+pub fn gen_all_hydrocarbons<const N: usize>(
+) -> Result<Vec<AdjacencyTwoBitsMatrix<N, StorageOf<N>>>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    enumerate(EnumerationConfig::default())
+}
+
+/// 炭素数 `N` の炭化水素の構造異性体を列挙し, 生成された順に `sink` へ渡す
+pub fn enumerate_into<const N: usize>(
+    config: EnumerationConfig<N>,
+    sink: impl IsomerSink<N, StorageOf<N>>,
+) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.validate()?;
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = config.create_feat2skeletons()?;
+    dehydrogenate_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        &config.dehydrogenation_options(),
+        sink,
+    )
+}
+
+/// 炭素数 `N` の炭化水素の炭素骨格 (結合次数を区別しないグラフ) を, 同型なものを除いて全て列挙する.
+/// 環と水素数の条件は骨格で判定できる範囲で適用され, フィルタや化学的妥当性の条件は適用されない
+pub fn enumerate_skeletons<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<AdjacencyBitMatrix<N, StorageOf<N>>>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.validate()?;
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = config.create_feat2skeletons()?;
+    let mut skeletons = Vec::new();
+    deduplicate_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        |skeleton| {
+            skeletons.push(skeleton);
+            Ok(())
+        },
+    )?;
+    Ok(skeletons)
+}
+
+/// 炭素数 `N` の炭化水素の構造異性体を, 保持せずに水素数ごとに数える.
+/// 返り値の添字が水素数に対応する
+pub fn count_isomers<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<usize>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.validate()?;
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = config.create_feat2skeletons()?;
+    let (counts, _) = count_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        &config.dehydrogenation_options(),
+        false,
+    )?;
+    Ok(counts)
+}
+
+/// `count_isomers` と同じく水素数ごとに数え, `no_cumulated_double_bonds` の規則で除かれた異性体の数も返す.
+/// 除かれた異性体は規則で打ち切る枝を同じ探索で辿って数えるので, 列挙は1回で済む.
+/// 規則が無効なら2つ目の返り値は全て 0 になる
+pub fn count_isomers_with_excluded<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<(Vec<usize>, Vec<usize>), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.validate()?;
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = config.create_feat2skeletons()?;
+    count_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        &config.dehydrogenation_options(),
+        true,
+    )
+}
+
+/// `count_columns` で異性体の数と併せて求める列
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Columns {
+    /// `no_cumulated_double_bonds` の規則で除かれた異性体の数
+    pub excluded: bool,
+    /// 芳香族の異性体の数
+    pub aromatic: bool,
+    /// 水素を指定した数だけ置換基に置き換えた位置異性体の数
+    pub substituted: Option<usize>,
+    /// 鏡像異性体を区別した立体異性体の数
+    pub stereoisomers: bool,
+    /// 鏡像に関する分類ごとの構造異性体の数
+    pub chirality: bool,
+}
+
+/// `count_columns` の結果. 添字が水素数に対応し, 求めなかった列は `None` になる
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnCounts {
+    pub counts: Vec<usize>,
+    pub excluded: Option<Vec<usize>>,
+    pub aromatic: Option<Vec<usize>>,
+    pub substituted: Option<Vec<usize>>,
+    pub stereoisomers: Option<Vec<usize>>,
+    pub chirality: Option<Vec<ChiralityCounts>>,
+}
+
+// 異性体1つについて求めた列の値
+struct IsomerColumns {
+    aromatic: bool,
+    substituted: usize,
+    stereo: Option<StereoAnalysis>,
+}
+
+/// `count_isomers` と同じく水素数ごとに数え, `columns` で指定した列も同じ列挙で求める.
+/// 解析の要る列があれば異性体ごとに自己同型群を求めるので, 数えるだけの場合より遅い
+pub fn count_columns<const N: usize>(
+    mut config: EnumerationConfig<N>,
+    columns: Columns,
+) -> Result<ColumnCounts, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let needs_analysis = columns.aromatic
+        || columns.substituted.is_some()
+        || columns.stereoisomers
+        || columns.chirality;
+    if !needs_analysis {
+        let (counts, excluded) = if columns.excluded {
+            count_isomers_with_excluded(config)?
+        } else {
+            (count_isomers(config)?, Vec::new())
+        };
+        return Ok(ColumnCounts {
+            counts,
+            excluded: columns.excluded.then_some(excluded),
+            ..Default::default()
+        });
+    }
+
+    // count_isomers_with_excluded と同じく, 規則を外して列挙し見つかった異性体を振り分ける
+    let count_excluded = columns.excluded && config.plausibility.no_cumulated_double_bonds;
+    config.plausibility.no_cumulated_double_bonds &= !count_excluded;
+    let analyze_isomer = |hydrocarbon: &AdjacencyTwoBitsMatrix<N, StorageOf<N>>,
+                          automorphisms: &[Permutation<N>],
+                          symmetries: &[Permutation<N>]| {
+        if count_excluded && has_cumulated_double_bonds(hydrocarbon) {
+            return Ok::<_, EnumerationError>(None);
+        }
+        let stereo = (columns.stereoisomers || columns.chirality)
+            .then(|| StereoAnalysis::new(hydrocarbon, automorphisms))
+            .transpose()?;
+        let substituted = columns
+            .substituted
+            .map(|k| Symmetry::new(hydrocarbon, symmetries).substitutions(k))
+            .transpose()?
+            .map_or(0, |substitutions| substitutions.len());
+        Ok(Some(IsomerColumns {
+            aromatic: columns.aromatic && is_aromatic(hydrocarbon),
+            substituted,
+            stereo,
+        }))
+    };
+
+    let zeros = || vec![0; 2 * N + 3];
+    let mut result = ColumnCounts {
+        counts: zeros(),
+        excluded: columns.excluded.then(zeros),
+        aromatic: columns.aromatic.then(zeros),
+        substituted: columns.substituted.map(|_| zeros()),
+        stereoisomers: columns.stereoisomers.then(zeros),
+        chirality: columns
+            .chirality
+            .then(|| vec![ChiralityCounts::default(); 2 * N + 3]),
+    };
+    analyze(config, analyze_isomer, |hydrocarbon, analyzed| {
+        let num_h = hydrocarbon.count_hydrogen();
+        let Some(analyzed) = analyzed? else {
+            if let Some(excluded) = &mut result.excluded {
+                excluded[num_h] += 1;
+            }
+            return Ok(());
+        };
+        result.counts[num_h] += 1;
+        if let Some(aromatic) = &mut result.aromatic {
+            aromatic[num_h] += analyzed.aromatic as usize;
+        }
+        if let Some(substituted) = &mut result.substituted {
+            substituted[num_h] += analyzed.substituted;
+        }
+        if let (Some(stereoisomers), Some(stereo)) = (&mut result.stereoisomers, &analyzed.stereo) {
+            stereoisomers[num_h] += stereo.count();
+        }
+        if let (Some(chirality), Some(stereo)) = (&mut result.chirality, &analyzed.stereo) {
+            chirality[num_h].add(stereo.chirality());
+        }
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// 炭素数 `N` の炭化水素の構造異性体を列挙し, それぞれの立体異性の解析結果を添えて返す
+pub fn enumerate_stereoisomers<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<(AdjacencyTwoBitsMatrix<N, StorageOf<N>>, StereoAnalysis)>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut analyzed = Vec::new();
+    analyze_stereo(config, |hydrocarbon, analysis| {
+        analyzed.push((hydrocarbon, analysis));
+        Ok(())
+    })?;
+    Ok(analyzed)
+}
+
+/// 炭素数 `N` の炭化水素の立体異性体を, 鏡像異性体を区別して水素数ごとに数える.
+/// 返り値の添字が水素数に対応する
+pub fn count_stereoisomers<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<usize>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut counts = vec![0; 2 * N + 3];
+    analyze_stereo(config, |hydrocarbon, analysis| {
+        counts[hydrocarbon.count_hydrogen()] += analysis.count();
+        Ok(())
+    })?;
+    Ok(counts)
+}
+
+/// 炭素数 `N` の炭化水素の構造異性体を, 鏡像に関する分類ごとに水素数ごとに数える.
+/// 返り値の添字が水素数に対応する
+pub fn count_chirality<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<ChiralityCounts>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut counts = vec![ChiralityCounts::default(); 2 * N + 3];
+    analyze_stereo(config, |hydrocarbon, analysis| {
+        counts[hydrocarbon.count_hydrogen()].add(analysis.chirality());
+        Ok(())
+    })?;
+    Ok(counts)
+}
+
+/// 炭素数 `N` の炭化水素の構造異性体を, 自己同型群と併せて全て列挙する.
+/// `collapse_resonance` を有効にすると, 芳香環の Kekulé 構造を区別しない自己同型群を用いる
+pub fn enumerate_with_symmetry<const N: usize>(
+    config: EnumerationConfig<N>,
+) -> Result<Vec<(AdjacencyTwoBitsMatrix<N, StorageOf<N>>, Symmetry<N>)>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut result = Vec::new();
+    let symmetry = |h: &_, _: &_, symmetries: &_| Symmetry::new(h, symmetries);
+    analyze(config, symmetry, |hydrocarbon, symmetry| {
+        result.push((hydrocarbon, symmetry));
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// 炭化水素と, その水素を置換基に置き換えた位置異性体
+pub type SubstitutionIsomers<const N: usize> = (
+    AdjacencyTwoBitsMatrix<N, StorageOf<N>>,
+    Vec<Substitution<N>>,
+);
+
+/// 炭化水素の水素を `k` 個置換基 X に置き換えた位置異性体を, 元の炭化水素ごとに全て列挙する.
+/// 置換基どうしの結合や置換基の立体配置は考えない
+pub fn enumerate_substitution_isomers<const N: usize>(
+    k: usize,
+    config: EnumerationConfig<N>,
+) -> Result<Vec<SubstitutionIsomers<N>>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut result = Vec::new();
+    let substitute = |h: &_, _: &_, symmetries: &_| Symmetry::new(h, symmetries).substitutions(k);
+    analyze(config, substitute, |hydrocarbon, substitutions| {
+        result.push((hydrocarbon, substitutions?));
+        Ok(())
+    })?;
+    Ok(result)
+}
+
+/// 水素を `k` 個置換基に置き換えた位置異性体を, 元の炭化水素の水素数ごとに数える.
+/// 返り値の添字が元の炭化水素の水素数に対応する
+pub fn count_substitution_isomers<const N: usize>(
+    k: usize,
+    config: EnumerationConfig<N>,
+) -> Result<Vec<usize>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut counts = vec![0; 2 * N + 3];
+    let substitute = |h: &_, _: &_, symmetries: &_| Symmetry::new(h, symmetries).substitutions(k);
+    analyze(config, substitute, |hydrocarbon, substitutions| {
+        counts[hydrocarbon.count_hydrogen()] += substitutions?.len();
+        Ok(())
+    })?;
+    Ok(counts)
+}
+
+fn analyze_stereo<const N: usize>(
+    config: EnumerationConfig<N>,
+    mut consume: impl FnMut(
+        AdjacencyTwoBitsMatrix<N, StorageOf<N>>,
+        StereoAnalysis,
+    ) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let stereo = |h: &_, automorphisms: &_, _: &_| StereoAnalysis::new(h, automorphisms);
+    analyze(config, stereo, |hydrocarbon, analysis| {
+        consume(hydrocarbon, analysis?)
+    })
+}
+
+// 炭化水素ごとに, 自己同型群の生成元を用いた解析 `analyze` の結果を `consume` に渡す.
+// `analyze` の引数は `analyze_feat2skeletons` と同じ
+fn analyze<const N: usize, T: Send>(
+    config: EnumerationConfig<N>,
+    analyze: impl Fn(&AdjacencyTwoBitsMatrix<N, StorageOf<N>>, &[Permutation<N>], &[Permutation<N>]) -> T
+        + Sync,
+    consume: impl FnMut(AdjacencyTwoBitsMatrix<N, StorageOf<N>>, T) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.validate()?;
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = config.create_feat2skeletons()?;
+    analyze_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        &config.dehydrogenation_options(),
+        analyze,
+        consume,
+    )
+}
+
+/// 分子式 `formula` の構造異性体を全て列挙する. `N` は水素以外の原子の数に等しくなければならない.
+/// `config.num_hydrogens` は無視し, 分子式の水素数を用いる
+pub fn enumerate_formula<const N: usize>(
+    formula: &Formula,
+    mut config: EnumerationConfig<N>,
+) -> Result<Vec<Molecule<N, StorageOf<N>>>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.num_hydrogens = None;
+    config.validate()?;
+    if formula.num_heavy_atoms() != N {
+        return Err(EnumerationError::HeavyAtomCountMismatch {
+            formula: formula.num_heavy_atoms(),
+            vertices: N,
+        });
+    }
+    let Some(unsaturation) = formula.degree_of_unsaturation() else {
+        return Ok(Vec::new());
+    };
+
+    // 環の数は不飽和度を超えない
+    let mut constraints = config.skeleton_constraints();
+    constraints.max_rings = constraints.max_rings.min(unsaturation);
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = create_feat2skeletons(config.fix_digits, constraints, config.num_threads)?;
+    let mut molecules = Vec::new();
+    dehydrogenate_colored_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        &config.dehydrogenation_options(),
+        formula,
+        |molecule| {
+            molecules.push(molecule);
+            Ok(())
+        },
+    )?;
+    Ok(molecules)
+}
+
+/// 炭素数 `N` で, 開いた原子価を `num_open_valences` 個持つ化学種を全て列挙する.
+/// 例えば C4H9 ラジカルや C5H11+ カルボカチオンは `num_open_valences = 1` で得られる.
+/// `collapse_resonance` は適用しない
+pub fn enumerate_species<const N: usize>(
+    num_open_valences: usize,
+    config: EnumerationConfig<N>,
+) -> Result<Vec<Species<N, StorageOf<N>>>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut species = Vec::new();
+    for_each_species(num_open_valences, config, |s| {
+        species.push(s);
+        Ok(())
+    })?;
+    Ok(species)
+}
+
+/// 開いた原子価を `num_open_valences` 個持つ化学種を, 水素数ごとに数える.
+/// 返り値の添字が水素数に対応する
+pub fn count_species<const N: usize>(
+    num_open_valences: usize,
+    config: EnumerationConfig<N>,
+) -> Result<Vec<usize>, EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    let mut counts = vec![0; 2 * N + 3];
+    for_each_species(num_open_valences, config, |species| {
+        counts[species.count_hydrogen()] += 1;
+        Ok(())
+    })?;
+    Ok(counts)
+}
+
+fn for_each_species<const N: usize>(
+    num_open_valences: usize,
+    config: EnumerationConfig<N>,
+    sink: impl FnMut(Species<N, StorageOf<N>>) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError>
+where
+    Carbon<N>: SelectStorage,
+{
+    config.validate()?;
+    let perm_store = PermutationStore::new();
+    let feat2skeletons = config.create_feat2skeletons()?;
+    dehydrogenate_open_feat2skeletons(
+        feat2skeletons,
+        &perm_store,
+        config.max_num_feats,
+        config.num_threads,
+        &config.dehydrogenation_options(),
+        num_open_valences,
+        sink,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_is_valid() {
+        assert!(EnumerationConfig::<MIN_CARBONS>::default()
+            .validate()
+            .is_ok());
+        assert!(EnumerationConfig::<35>::default().validate().is_ok());
+        assert!(EnumerationConfig::<MAX_CARBONS>::default()
+            .validate()
+            .is_ok());
+        for num_threads in [1, 2, 3, 64, 1 << 20] {
+            let fix_digits =
+                EnumerationConfig::<MAX_CARBONS>::default_fix_digits(Some(num_threads));
+            assert!(fix_digits <= MAX_FIX_DIGITS, "{num_threads} threads");
+            // 1スレッドあたり少なくとも 2^EXTRA_FIX_DIGITS 個の探索に分ける
+            assert!(
+                1 << fix_digits >= num_threads << EXTRA_FIX_DIGITS || fix_digits == MAX_FIX_DIGITS
+            );
+        }
+        assert_eq!(EnumerationConfig::<4>::default_fix_digits(Some(64)), 4);
+    }
+
+    #[test]
+    fn invalid_ring_counts() {
+        let config = EnumerationConfig::<6> {
+            acyclic: true,
+            exact_rings: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            count_isomers(config),
+            Err(EnumerationError::InvalidRingCounts { exact: 1, max: 0 })
+        );
+    }
+
+    #[test]
+    fn invalid_ring_sizes() {
+        let config = EnumerationConfig::<6> {
+            min_ring_size: Some(6),
+            max_ring_size: Some(5),
+            ..Default::default()
+        };
+        assert_eq!(
+            count_isomers(config),
+            Err(EnumerationError::InvalidRingSizes { min: 6, max: 5 })
+        );
+    }
+
+    fn assert_same_columns<const N: usize>(no_cumulated: bool)
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let config = || EnumerationConfig::<N> {
+            plausibility: PlausibilityRules {
+                no_cumulated_double_bonds: no_cumulated,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let columns = Columns {
+            excluded: true,
+            aromatic: true,
+            substituted: Some(2),
+            stereoisomers: true,
+            chirality: true,
+        };
+        let all = count_columns(config(), columns).unwrap();
+        let context = format!("C{N}, no_cumulated = {no_cumulated}");
+
+        let (counts, excluded) = count_isomers_with_excluded(config()).unwrap();
+        assert_eq!(all.counts, counts, "{context}");
+        assert_eq!(all.excluded, Some(excluded), "{context}");
+        let mut aromatic = config();
+        aromatic.filters.push(Box::new(is_aromatic));
+        assert_eq!(
+            all.aromatic,
+            Some(count_isomers(aromatic).unwrap()),
+            "{context}"
+        );
+        let substituted = count_substitution_isomers(2, config()).unwrap();
+        assert_eq!(all.substituted, Some(substituted), "{context}");
+        let stereoisomers = count_stereoisomers(config()).unwrap();
+        assert_eq!(all.stereoisomers, Some(stereoisomers), "{context}");
+        assert_eq!(
+            all.chirality,
+            Some(count_chirality(config()).unwrap()),
+            "{context}"
+        );
+
+        // 解析の要る列がなければ数えるだけで済ませる
+        let counts_only = count_columns(
+            config(),
+            Columns {
+                excluded: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(counts_only.counts, all.counts, "{context}");
+        assert_eq!(counts_only.excluded, all.excluded, "{context}");
+        assert_eq!(counts_only.aromatic, None, "{context}");
+    }
+
+    #[test]
+    fn columns_in_one_pass() {
+        for no_cumulated in [false, true] {
+            assert_same_columns::<4>(no_cumulated);
+            assert_same_columns::<5>(no_cumulated);
+            assert_same_columns::<6>(no_cumulated);
+        }
+    }
+
+    // Kekulé 構造をまとめる対称操作は二重結合を単結合に移しうるので, 立体異性の解析には用いない
+    #[test]
+    fn stereo_with_collapsed_resonance() {
+        let config = || EnumerationConfig::<10> {
+            num_hydrogens: Some(10),
+            max_rings: Some(1),
+            collapse_resonance: true,
+            ..Default::default()
+        };
+        let columns = Columns {
+            substituted: Some(1),
+            stereoisomers: true,
+            chirality: true,
+            ..Default::default()
+        };
+        let all = count_columns(config(), columns).unwrap();
+        assert_eq!(all.counts[10], 13747);
+        let stereoisomers = count_stereoisomers(config()).unwrap();
+        assert_eq!(stereoisomers[10], 27683);
+        assert_eq!(all.stereoisomers, Some(stereoisomers));
+        assert_eq!(all.chirality, Some(count_chirality(config()).unwrap()));
+        let substituted = count_substitution_isomers(1, config()).unwrap();
+        assert_eq!(all.substituted, Some(substituted));
+    }
+}
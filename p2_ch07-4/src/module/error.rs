@@ -0,0 +1,142 @@
+use std::fmt;
+
+/// 炭化水素の列挙中に発生しうるエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnumerationError {
+    /// 炭素数が対応範囲外
+    UnsupportedCarbons { carbons: usize },
+    /// 固定する桁数が大きすぎる
+    FixDigitsTooLarge { fix_digits: usize, max: usize },
+    /// `max_num_feats` が 0
+    ZeroMaxNumFeats,
+    /// スレッド数が 0
+    ZeroThreads,
+    /// 環の大きさの下限が上限を超えている
+    InvalidRingSizes { min: usize, max: usize },
+    /// 指定した環の数が上限を超えている
+    InvalidRingCounts { exact: usize, max: usize },
+    /// 置き換える水素の数が大きすぎる
+    TooManySubstitutions { count: usize },
+    /// 分子式を読めない
+    InvalidFormula { formula: String },
+    /// 分子式の水素以外の原子の数が, 列挙する頂点数と異なる
+    HeavyAtomCountMismatch { formula: usize, vertices: usize },
+    /// SMILES の文法が誤っている. `position` は問題の箇所のバイト位置
+    InvalidSmiles { smiles: String, position: usize },
+    /// 炭化水素の範囲外の SMILES 記法 (他の元素, 芳香族原子, 角括弧, 立体配置など) が含まれる
+    UnsupportedSmiles { smiles: String, position: usize },
+    /// SMILES の炭素の原子価が 4 を超える
+    ValenceExceeded { smiles: String, position: usize },
+    /// SMILES の炭素数が, 行列の頂点数と異なる
+    CarbonCountMismatch { smiles: usize, vertices: usize },
+    /// graph6 または sparse6 の文字列を読めない
+    InvalidGraphString { graph: String },
+    /// ループ, 扱えない多重度の辺, 次数が 4 を超える頂点のいずれかを含むグラフ
+    UnsupportedGraph { graph: String },
+    /// 連結でないグラフ. 炭素骨格は1つの分子を表す
+    DisconnectedGraph { graph: String },
+    /// グラフの頂点数が, 行列の頂点数と異なる
+    VertexCountMismatch { graph: usize, vertices: usize },
+    /// 立体単位に作用する対称操作が多すぎて, 立体異性体を数えられない
+    TooManySymmetries { max: usize },
+    /// 対称操作として与えた置換が, 立体単位を立体単位に移さない
+    StereoUnitsNotPreserved,
+    /// 特徴量に対応する置換の生成元が見つからない
+    MissingPermutations,
+    /// ワーカースレッドとの通信が途絶えた
+    WorkerDisconnected,
+    /// ワーカースレッドが panic した
+    WorkerPanicked,
+    /// 結果の書き出しに失敗した
+    Io(String),
+}
+
+impl fmt::Display for EnumerationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedCarbons { carbons } => write!(
+                f,
+                "carbon count {carbons} is not supported: supported range is {}..={}",
+                super::MIN_CARBONS,
+                super::MAX_CARBONS
+            ),
+            Self::FixDigitsTooLarge { fix_digits, max } => {
+                write!(f, "fix_digits {fix_digits} is too large: maximum is {max}")
+            }
+            Self::ZeroMaxNumFeats => write!(f, "max_num_feats must be positive"),
+            Self::ZeroThreads => write!(f, "number of threads must be positive"),
+            Self::InvalidRingSizes { min, max } => {
+                write!(f, "minimum ring size {min} exceeds maximum ring size {max}")
+            }
+            Self::InvalidRingCounts { exact, max } => {
+                write!(
+                    f,
+                    "exact ring count {exact} exceeds maximum ring count {max}"
+                )
+            }
+            Self::TooManySubstitutions { count } => {
+                write!(f, "cannot replace {count} hydrogens: count is too large")
+            }
+            Self::InvalidFormula { formula } => write!(f, "invalid molecular formula `{formula}`"),
+            Self::HeavyAtomCountMismatch { formula, vertices } => write!(
+                f,
+                "formula has {formula} non-hydrogen atoms but {vertices} vertices were requested"
+            ),
+            Self::InvalidSmiles { smiles, position } => {
+                write!(f, "invalid SMILES `{smiles}` at position {position}")
+            }
+            Self::UnsupportedSmiles { smiles, position } => write!(
+                f,
+                "unsupported SMILES `{smiles}` at position {position}: \
+                 only unbracketed aliphatic carbons are supported"
+            ),
+            Self::ValenceExceeded { smiles, position } => write!(
+                f,
+                "carbon at position {position} of SMILES `{smiles}` has more than 4 bonds"
+            ),
+            Self::CarbonCountMismatch { smiles, vertices } => write!(
+                f,
+                "SMILES has {smiles} carbons but {vertices} vertices were requested"
+            ),
+            Self::InvalidGraphString { graph } => {
+                write!(f, "invalid graph6 or sparse6 string `{graph}`")
+            }
+            Self::UnsupportedGraph { graph } => write!(
+                f,
+                "graph `{graph}` is not a carbon skeleton: \
+                 it has a loop, too many parallel edges, or a vertex of degree above 4"
+            ),
+            Self::DisconnectedGraph { graph } => {
+                write!(
+                    f,
+                    "graph `{graph}` is not a carbon skeleton: it is disconnected"
+                )
+            }
+            Self::VertexCountMismatch { graph, vertices } => write!(
+                f,
+                "graph has {graph} vertices but {vertices} vertices were requested"
+            ),
+            Self::TooManySymmetries { max } => write!(
+                f,
+                "too many symmetries act on the stereo units: maximum is {max}"
+            ),
+            Self::StereoUnitsNotPreserved => {
+                write!(f, "a symmetry does not map stereo units to stereo units")
+            }
+            Self::MissingPermutations => {
+                write!(f, "no permutation generators found for a feature key")
+            }
+            Self::WorkerDisconnected => write!(f, "worker thread disconnected unexpectedly"),
+            Self::WorkerPanicked => write!(f, "worker thread panicked"),
+            Self::Io(message) => write!(f, "I/O error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for EnumerationError {}
+
+impl From<std::io::Error> for EnumerationError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
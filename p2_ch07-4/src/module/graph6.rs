@@ -0,0 +1,436 @@
+use super::{
+    error::EnumerationError,
+    matrix::{AdjacencyBitMatrix, AdjacencyTwoBitsMatrix},
+    storage::Storage,
+};
+
+// graph6 と sparse6 の各文字は 6 ビットの値に 63 を足したもの
+const BIAS: u8 = 63;
+const SPARSE6_PREFIX: char = ':';
+const GRAPH6_HEADER: &str = ">>graph6<<";
+const SPARSE6_HEADER: &str = ">>sparse6<<";
+
+impl<const N: usize, S: Storage> AdjacencyBitMatrix<N, S> {
+    /// nauty の graph6 形式の文字列. 改行は含まない
+    pub fn to_graph6(&self) -> String {
+        let mut writer = BitWriter::new();
+        write_size(&mut writer.text, N);
+        for j in 1..N {
+            for i in 0..j {
+                writer.push(self.is_adjacent(i, j));
+            }
+        }
+        writer.pad(false);
+        writer.text
+    }
+
+    /// nauty の sparse6 形式の文字列. 改行は含まない
+    pub fn to_sparse6(&self) -> String {
+        let edges: Vec<_> = (0..N)
+            .flat_map(|v| (0..v).map(move |u| (u, v)))
+            .filter(|&(u, v)| self.is_adjacent(u, v))
+            .collect();
+        encode_sparse6(N, &edges)
+    }
+
+    /// graph6 形式の炭素骨格を読む. 頂点数が `N` に一致し, 次数が 4 以下の連結グラフである必要がある
+    pub fn from_graph6(graph: &str) -> Result<Self, EnumerationError> {
+        let multiplicities = decode_graph6(graph)
+            .ok_or_else(|| invalid(graph))
+            .and_then(|(n, edges)| count_multiplicities::<N>(graph, n, &edges, 1))?;
+        Ok(Self::from_multiplicities(&multiplicities))
+    }
+
+    /// sparse6 形式の炭素骨格を読む. 多重辺とループは扱えず, 連結グラフである必要がある
+    pub fn from_sparse6(graph: &str) -> Result<Self, EnumerationError> {
+        let multiplicities = decode_sparse6(graph)
+            .ok_or_else(|| invalid(graph))
+            .and_then(|(n, edges)| count_multiplicities::<N>(graph, n, &edges, 1))?;
+        Ok(Self::from_multiplicities(&multiplicities))
+    }
+
+    fn from_multiplicities(multiplicities: &[[u32; N]; N]) -> Self {
+        let mut skeleton = Self::empty();
+        for (i, row) in multiplicities.iter().enumerate() {
+            for j in (i + 1..N).filter(|&j| row[j] != 0) {
+                skeleton.flip_at(i, j);
+            }
+        }
+        skeleton
+    }
+}
+
+impl<const N: usize, S: Storage> AdjacencyTwoBitsMatrix<N, S> {
+    /// 結合次数を辺の多重度とした多重グラフの sparse6 形式の文字列. 改行は含まない
+    pub fn to_sparse6(&self) -> String {
+        let edges: Vec<_> = (0..N)
+            .flat_map(|v| (0..v).map(move |u| (u, v)))
+            .flat_map(|(u, v)| (0..self.element_at(u, v)).map(move |_| (u, v)))
+            .collect();
+        encode_sparse6(N, &edges)
+    }
+
+    /// 辺の多重度を結合次数とみなして, sparse6 形式の多重グラフを読む.
+    /// 多重度は 3 以下, 各頂点の多重度の和は 4 以下で, 連結グラフである必要がある
+    pub fn from_sparse6(graph: &str) -> Result<Self, EnumerationError> {
+        let multiplicities = decode_sparse6(graph)
+            .ok_or_else(|| invalid(graph))
+            .and_then(|(n, edges)| count_multiplicities::<N>(graph, n, &edges, 3))?;
+        let mut hydrocarbon = Self::empty();
+        for (i, row) in multiplicities.iter().enumerate() {
+            for (j, &multiplicity) in row.iter().enumerate().skip(i + 1) {
+                for _ in 0..multiplicity {
+                    hydrocarbon.increment_at(i, j);
+                }
+            }
+        }
+        Ok(hydrocarbon)
+    }
+}
+
+fn invalid(graph: &str) -> EnumerationError {
+    EnumerationError::InvalidGraphString {
+        graph: graph.to_string(),
+    }
+}
+
+// 辺の一覧を頂点対ごとの多重度にまとめ, 炭素骨格として扱えるか確かめる.
+// SMILES などの出力は1つの分子を前提とするので, 非連結なグラフは受け付けない
+fn count_multiplicities<const N: usize>(
+    graph: &str,
+    num_vertices: usize,
+    edges: &[(usize, usize)],
+    max_multiplicity: u32,
+) -> Result<[[u32; N]; N], EnumerationError> {
+    if num_vertices != N {
+        return Err(EnumerationError::VertexCountMismatch {
+            graph: num_vertices,
+            vertices: N,
+        });
+    }
+    let unsupported = || EnumerationError::UnsupportedGraph {
+        graph: graph.to_string(),
+    };
+    let mut multiplicities = [[0; N]; N];
+    for &(u, v) in edges {
+        if u == v {
+            return Err(unsupported());
+        }
+        multiplicities[u][v] += 1;
+        multiplicities[v][u] += 1;
+        if multiplicities[u][v] > max_multiplicity {
+            return Err(unsupported());
+        }
+    }
+    if multiplicities.iter().any(|row| row.iter().sum::<u32>() > 4) {
+        return Err(unsupported());
+    }
+    if !is_connected(&multiplicities) {
+        return Err(EnumerationError::DisconnectedGraph {
+            graph: graph.to_string(),
+        });
+    }
+    Ok(multiplicities)
+}
+
+fn is_connected<const N: usize>(multiplicities: &[[u32; N]; N]) -> bool {
+    let mut visited = [false; N];
+    let mut stack = vec![0];
+    visited[0] = true;
+    while let Some(u) = stack.pop() {
+        for v in 0..N {
+            if multiplicities[u][v] != 0 && !visited[v] {
+                visited[v] = true;
+                stack.push(v);
+            }
+        }
+    }
+    visited.iter().all(|&v| v)
+}
+
+// 辺 (u, v) (u <= v) の一覧を sparse6 形式にする. 同じ辺を繰り返すと多重辺になる
+fn encode_sparse6(n: usize, edges: &[(usize, usize)]) -> String {
+    let k = bit_width(n);
+    let mut edges = edges.to_vec();
+    edges.sort_by_key(|&(u, v)| (v, u));
+
+    let mut writer = BitWriter::new();
+    writer.text.push(SPARSE6_PREFIX);
+    write_size(&mut writer.text, n);
+    let mut current = 0;
+    for (u, v) in edges {
+        if v == current + 1 {
+            writer.push(true);
+            writer.push_value(u, k);
+            current = v;
+            continue;
+        }
+        if v > current {
+            writer.push(true);
+            writer.push_value(v, k);
+            current = v;
+        }
+        writer.push(false);
+        writer.push_value(u, k);
+    }
+    // 末尾の埋め草が頂点 n - 1 への辺と読まれないよう, 必要なら 0 から始める
+    let padding = (6 - writer.len % 6) % 6;
+    if k < 6 && n == 1 << k && current + 2 == n && padding > k {
+        writer.push(false);
+    }
+    writer.pad(true);
+    writer.text
+}
+
+// graph6 形式を頂点数と辺の一覧にする
+fn decode_graph6(graph: &str) -> Option<(usize, Vec<(usize, usize)>)> {
+    let body = graph.strip_prefix(GRAPH6_HEADER).unwrap_or(graph);
+    let (n, data) = read_size(body.as_bytes())?;
+    // 頂点数は入力の先頭から読むので, 巨大な値でもあふれないよう検査する
+    let num_bits = n.checked_mul(n.saturating_sub(1))? / 2;
+    if data.len() != num_bits.div_ceil(6) {
+        return None;
+    }
+    let mut bits = read_bits(data)?.into_iter();
+    let mut edges = Vec::new();
+    for j in 1..n {
+        for i in 0..j {
+            if bits.next()? {
+                edges.push((i, j));
+            }
+        }
+    }
+    // 埋め草は 0 でなければならない
+    bits.all(|bit| !bit).then_some((n, edges))
+}
+
+// sparse6 形式を頂点数と辺の一覧にする. 多重辺は同じ辺の繰り返しになる
+fn decode_sparse6(graph: &str) -> Option<(usize, Vec<(usize, usize)>)> {
+    let body = graph.strip_prefix(SPARSE6_HEADER).unwrap_or(graph);
+    let body = body.strip_prefix(SPARSE6_PREFIX)?;
+    let (n, data) = read_size(body.as_bytes())?;
+    let k = bit_width(n);
+    let bits = read_bits(data)?;
+    let mut edges = Vec::new();
+    let mut current = 0;
+    for chunk in bits.chunks_exact(k + 1) {
+        if chunk[0] {
+            current += 1;
+        }
+        let x = chunk[1..]
+            .iter()
+            .fold(0, |acc, &bit| 2 * acc + bit as usize);
+        if x >= n || current >= n {
+            break;
+        }
+        if x > current {
+            current = x;
+        } else {
+            edges.push((x, current));
+        }
+    }
+    Some((n, edges))
+}
+
+// 頂点番号 0..n を表すのに必要なビット数
+fn bit_width(n: usize) -> usize {
+    let mut k = 0;
+    while (1 << k) < n {
+        k += 1;
+    }
+    k
+}
+
+// 頂点数 n を 1, 4, 8 文字のいずれかで書く
+fn write_size(text: &mut String, n: usize) {
+    let digits = match n {
+        0..=62 => {
+            text.push((n as u8 + BIAS) as char);
+            return;
+        }
+        63..=258047 => {
+            text.push('~');
+            3
+        }
+        _ => {
+            text.push_str("~~");
+            6
+        }
+    };
+    for i in (0..digits).rev() {
+        text.push((((n >> (6 * i)) & 0b111111) as u8 + BIAS) as char);
+    }
+}
+
+fn read_size(bytes: &[u8]) -> Option<(usize, &[u8])> {
+    let (digits, rest) = match bytes {
+        [b'~', b'~', rest @ ..] => (6, rest),
+        [b'~', rest @ ..] => (3, rest),
+        [_, ..] => (1, bytes),
+        [] => return None,
+    };
+    if rest.len() < digits {
+        return None;
+    }
+    let (size, data) = rest.split_at(digits);
+    let n = size.iter().try_fold(0, |acc, &c| {
+        let value = c.checked_sub(BIAS).filter(|&x| x < 64)?;
+        Some(acc << 6 | value as usize)
+    })?;
+    Some((n, data))
+}
+
+// 各文字を 6 ビットとして, 上位ビットから順に並べる
+fn read_bits(data: &[u8]) -> Option<Vec<bool>> {
+    let mut bits = Vec::with_capacity(6 * data.len());
+    for &c in data {
+        let value = c.checked_sub(BIAS).filter(|&x| x < 64)?;
+        bits.extend((0..6).rev().map(|i| value >> i & 1 == 1));
+    }
+    Some(bits)
+}
+
+struct BitWriter {
+    text: String,
+    value: u8,
+    len: usize,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            text: String::new(),
+            value: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bit: bool) {
+        self.value = self.value << 1 | bit as u8;
+        self.len += 1;
+        if self.len.is_multiple_of(6) {
+            self.text.push((self.value + BIAS) as char);
+            self.value = 0;
+        }
+    }
+
+    // `value` の下位 `width` ビットを上位から書く
+    fn push_value(&mut self, value: usize, width: usize) {
+        for i in (0..width).rev() {
+            self.push(value >> i & 1 == 1);
+        }
+    }
+
+    // 6 ビットに満たない残りを `bit` で埋める. graph6 は 0 で, sparse6 は 1 で埋める
+    fn pad(&mut self, bit: bool) {
+        while !self.len.is_multiple_of(6) {
+            self.push(bit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::enumeration::{enumerate, enumerate_skeletons, EnumerationConfig},
+        *,
+    };
+
+    #[test]
+    fn graph6() {
+        // 三角形 (シクロプロパンの骨格)
+        let cyclopropane = AdjacencyBitMatrix::<3>::from_graph6("Bw").unwrap();
+        assert_eq!(cyclopropane.to_graph6(), "Bw");
+        assert_eq!(
+            AdjacencyTwoBitsMatrix::from(cyclopropane).count_hydrogen(),
+            6
+        );
+        assert_eq!(
+            AdjacencyBitMatrix::<3>::from_graph6(">>graph6<<Bw").unwrap(),
+            cyclopropane
+        );
+
+        // 骨格は次数 4 以下の連結グラフで, geng -c -D4 の出力と個数が一致する
+        let skeletons = enumerate_skeletons::<6>(EnumerationConfig::default()).unwrap();
+        assert_eq!(skeletons.len(), 78);
+        for skeleton in &skeletons {
+            let graph6 = skeleton.to_graph6();
+            assert_eq!(AdjacencyBitMatrix::from_graph6(&graph6).unwrap(), *skeleton);
+            let sparse6 = skeleton.to_sparse6();
+            assert_eq!(
+                AdjacencyBitMatrix::from_sparse6(&sparse6).unwrap(),
+                *skeleton
+            );
+        }
+
+        // 結合次数は多重辺として書く
+        let ethylene = AdjacencyTwoBitsMatrix::<2>::from_sparse6(":Ab").unwrap();
+        assert_eq!(ethylene.element_at(0, 1), 2);
+        for hydrocarbon in enumerate::<5>(EnumerationConfig::default()).unwrap() {
+            let sparse6 = hydrocarbon.to_sparse6();
+            assert_eq!(
+                AdjacencyTwoBitsMatrix::from_sparse6(&sparse6).unwrap(),
+                hydrocarbon
+            );
+        }
+
+        let unsupported = |graph: &str| EnumerationError::UnsupportedGraph {
+            graph: graph.to_string(),
+        };
+        assert_eq!(
+            AdjacencyBitMatrix::<2>::from_sparse6(":Ab").unwrap_err(),
+            unsupported(":Ab")
+        );
+        // K5 は次数 4 なので読めるが, K6 は次数 5 の頂点を持つ
+        assert!(AdjacencyBitMatrix::<5>::from_graph6("D~{").is_ok());
+        assert_eq!(
+            AdjacencyBitMatrix::<6>::from_graph6("E~~w").unwrap_err(),
+            unsupported("E~~w")
+        );
+        assert_eq!(
+            AdjacencyBitMatrix::<4>::from_graph6("Bw").unwrap_err(),
+            EnumerationError::VertexCountMismatch {
+                graph: 3,
+                vertices: 4,
+            }
+        );
+        let invalid = |graph: &str| EnumerationError::InvalidGraphString {
+            graph: graph.to_string(),
+        };
+        for graph in ["", "B", "Bww", "Bx", ":Bw"] {
+            let error = AdjacencyBitMatrix::<3>::from_graph6(graph).unwrap_err();
+            assert_eq!(error, invalid(graph));
+        }
+        for graph in ["", ":", "Bw", ":B\x7f"] {
+            let error = AdjacencyTwoBitsMatrix::<3>::from_sparse6(graph).unwrap_err();
+            assert_eq!(error, invalid(graph));
+        }
+        // 頂点数が巨大なヘッダでもあふれずにエラーになる
+        assert_eq!(
+            AdjacencyBitMatrix::<4>::from_graph6("~~~~~~~~").unwrap_err(),
+            invalid("~~~~~~~~")
+        );
+
+        // 2分子のエタン (辺 0-1 と 2-3) や nauty の formats.txt の例のような
+        // 非連結なグラフは, 1つの炭素骨格として読まない
+        let disconnected = |graph: &str| EnumerationError::DisconnectedGraph {
+            graph: graph.to_string(),
+        };
+        assert_eq!(
+            AdjacencyBitMatrix::<7>::from_sparse6(":Fa@x^").unwrap_err(),
+            disconnected(":Fa@x^")
+        );
+        assert_eq!(
+            AdjacencyBitMatrix::<4>::from_sparse6(":CfV").unwrap_err(),
+            disconnected(":CfV")
+        );
+        assert_eq!(
+            AdjacencyTwoBitsMatrix::<4>::from_sparse6(":CfV").unwrap_err(),
+            disconnected(":CfV")
+        );
+        assert_eq!(
+            AdjacencyBitMatrix::<4>::from_graph6("CQ").unwrap_err(),
+            disconnected("CQ")
+        );
+    }
+}
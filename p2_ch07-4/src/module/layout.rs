@@ -0,0 +1,181 @@
+use std::f64::consts::PI;
+
+use super::{matrix::AdjacencyTwoBitsMatrix, storage::Storage};
+
+/// 2次元座標での C-C 結合の長さ (Å)
+pub const BOND_LENGTH: f64 = 1.5;
+
+// 応力最小化の反復回数の上限と, 収束とみなす座標の変化量
+const MAX_ITERATIONS: usize = 500;
+const TOLERANCE: f64 = 1e-6;
+
+/// 炭素の2次元座標. 結合の数で測った距離から, 120° のジグザグ鎖での距離を目標として
+/// 応力 Σ (|x_i - x_j| - t_ij)² / t_ij² を最小化する. 初期配置は古典的多次元尺度構成法で決める
+pub fn layout_2d<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+) -> [[f64; 2]; N] {
+    let distances = graph_distances(hydrocarbon);
+    let mut targets = [[0.0; N]; N];
+    for i in 0..N {
+        for j in 0..N {
+            targets[i][j] = zigzag_distance(distances[i][j]);
+        }
+    }
+    let mut positions = classical_scaling(&targets);
+    for _ in 0..MAX_ITERATIONS {
+        let mut max_shift: f64 = 0.0;
+        for i in 0..N {
+            let mut sum = [0.0; 2];
+            let mut sum_weights = 0.0;
+            for j in (0..N).filter(|&j| j != i) {
+                let weight = 1.0 / (targets[i][j] * targets[i][j]);
+                let diff = [
+                    positions[i][0] - positions[j][0],
+                    positions[i][1] - positions[j][1],
+                ];
+                let norm = diff[0].hypot(diff[1]);
+                for k in 0..2 {
+                    let pull = if norm > 0.0 {
+                        targets[i][j] * diff[k] / norm
+                    } else {
+                        0.0
+                    };
+                    sum[k] += weight * (positions[j][k] + pull);
+                }
+                sum_weights += weight;
+            }
+            if sum_weights == 0.0 {
+                continue;
+            }
+            let new = [sum[0] / sum_weights, sum[1] / sum_weights];
+            max_shift = max_shift.max((new[0] - positions[i][0]).hypot(new[1] - positions[i][1]));
+            positions[i] = new;
+        }
+        if max_shift < TOLERANCE {
+            break;
+        }
+    }
+
+    // 重心を原点に置く
+    let center = [0, 1].map(|k| positions.iter().map(|p| p[k]).sum::<f64>() / N as f64);
+    positions.map(|p| [p[0] - center[0], p[1] - center[1]])
+}
+
+/// 炭素 `idx` に結合する水素を置く向き (ラジアン). 隣接する炭素の間で最も広く空いた角度を等分する
+pub fn hydrogen_directions<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+    positions: &[[f64; 2]; N],
+    idx: usize,
+) -> Vec<f64> {
+    let num_hydrogens = 4 - hydrocarbon.degree_of(idx) as usize;
+    let mut angles: Vec<f64> = (0..N)
+        .filter(|&j| hydrocarbon.element_at(idx, j) != 0)
+        .map(|j| {
+            let dy = positions[j][1] - positions[idx][1];
+            let dx = positions[j][0] - positions[idx][0];
+            dy.atan2(dx)
+        })
+        .collect();
+    angles.sort_by(f64::total_cmp);
+    let (start, gap) = match angles.len() {
+        0 => (0.0, 2.0 * PI),
+        len => (0..len)
+            .map(|i| {
+                let next = if i + 1 < len {
+                    angles[i + 1]
+                } else {
+                    angles[0] + 2.0 * PI
+                };
+                (angles[i], next - angles[i])
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("angles is not empty"),
+    };
+    (1..=num_hydrogens)
+        .map(|i| start + gap * i as f64 / (num_hydrogens + 1) as f64)
+        .collect()
+}
+
+// 結合の数で測った原子間の距離
+fn graph_distances<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+) -> [[usize; N]; N] {
+    let mut distances = [[usize::MAX; N]; N];
+    for (source, row) in distances.iter_mut().enumerate() {
+        row[source] = 0;
+        let mut queue = vec![source];
+        let mut head = 0;
+        while head < queue.len() {
+            let u = queue[head];
+            head += 1;
+            for v in 0..N {
+                if hydrocarbon.element_at(u, v) != 0 && row[v] == usize::MAX {
+                    row[v] = row[u] + 1;
+                    queue.push(v);
+                }
+            }
+        }
+    }
+    distances
+}
+
+// 結合 `bonds` 本離れた原子の, 結合角 120° で平面に伸ばした鎖での距離
+fn zigzag_distance(bonds: usize) -> f64 {
+    let along = bonds as f64 * BOND_LENGTH * 3f64.sqrt() / 2.0;
+    let across = if bonds % 2 == 1 {
+        BOND_LENGTH / 2.0
+    } else {
+        0.0
+    };
+    along.hypot(across)
+}
+
+// 距離行列の二重中心化行列の上位2つの固有ベクトルから座標を作る
+fn classical_scaling<const N: usize>(targets: &[[f64; N]; N]) -> [[f64; 2]; N] {
+    let squared = targets.map(|row| row.map(|t| t * t));
+    let row_means = squared.map(|row| row.iter().sum::<f64>() / N as f64);
+    let total_mean = row_means.iter().sum::<f64>() / N as f64;
+    let mut gram = [[0.0; N]; N];
+    for i in 0..N {
+        for j in 0..N {
+            gram[i][j] = -0.5 * (squared[i][j] - row_means[i] - row_means[j] + total_mean);
+        }
+    }
+    // 固有値を正にずらして, べき乗法で代数的に大きい固有値から求める
+    let shift = gram
+        .iter()
+        .map(|row| row.iter().map(|x| x.abs()).sum::<f64>())
+        .fold(0.0, f64::max);
+
+    let mut positions = [[0.0; 2]; N];
+    let mut found: Vec<[f64; N]> = Vec::new();
+    for k in 0..2 {
+        // 対称な配置でも固有空間の一方に偏らないよう, 添字に依存する初期ベクトルを使う
+        let mut vector: [f64; N] = std::array::from_fn(|i| ((i + 1) * (k + 2)) as f64 % 7.0 + 1.0);
+        let mut unit = [0.0; N];
+        let mut eigenvalue = 0.0;
+        for _ in 0..MAX_ITERATIONS {
+            for prev in &found {
+                let dot: f64 = (0..N).map(|i| vector[i] * prev[i]).sum();
+                for i in 0..N {
+                    vector[i] -= dot * prev[i];
+                }
+            }
+            let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+            if norm == 0.0 {
+                break;
+            }
+            unit = vector.map(|x| x / norm);
+            vector = std::array::from_fn(|i| {
+                (0..N).map(|j| gram[i][j] * unit[j]).sum::<f64>() + shift * unit[i]
+            });
+            eigenvalue = (0..N).map(|i| vector[i] * unit[i]).sum::<f64>() - shift;
+        }
+        let scale = eigenvalue.max(0.0).sqrt();
+        for (position, x) in positions.iter_mut().zip(unit) {
+            position[k] = x * scale;
+        }
+        found.push(unit);
+    }
+    positions
+}
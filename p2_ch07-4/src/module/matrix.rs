@@ -2,48 +2,122 @@ use std::hash::{Hash, Hasher};
 
 use rustc_hash::FxHasher;
 
-use super::{hot_bit_iter::HotBitIter, permutation::Permutable};
+use super::{
+    hot_bit_iter::HotBitIter,
+    permutation::Permutable,
+    storage::{Bits, Storage, Storage16},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct AdjacencyBitMatrix<const N: usize> {
-    rows: [u16; N],
+pub struct AdjacencyBitMatrix<const N: usize, S: Storage = Storage16> {
+    rows: [S::Row; N],
 }
 
-impl<const N: usize> AdjacencyBitMatrix<N> {
-    const ZERO: Self = Self { rows: [0; N] };
+impl<const N: usize, S: Storage> AdjacencyBitMatrix<N, S> {
+    const ZERO: Self = Self {
+        rows: [S::Row::ZERO; N],
+    };
 
-    fn element_at(&self, irow: usize, icol: usize) -> u16 {
-        self.rows[irow] >> icol & 1
+    /// 辺を1つも持たない行列
+    pub fn empty() -> Self {
+        Self::ZERO
     }
 
-    fn flip_at(&mut self, irow: usize, icol: usize) {
-        self.rows[irow] ^= 1 << icol;
-        self.rows[icol] ^= 1 << irow;
+    fn element_at(&self, irow: usize, icol: usize) -> S::Row {
+        self.rows[irow] >> icol & S::Row::ONE
     }
 
-    const REPUNIT: u16 = {
-        let mut repunit = 0;
-        let mut i = 0;
-        while i < N {
-            repunit |= 1 << i;
-            i += 1;
-        }
-        repunit
-    };
+    pub fn is_adjacent(&self, irow: usize, icol: usize) -> bool {
+        self.element_at(irow, icol) != S::Row::ZERO
+    }
+
+    pub fn flip_at(&mut self, irow: usize, icol: usize) {
+        self.rows[irow] ^= S::Row::ONE << icol;
+        self.rows[icol] ^= S::Row::ONE << irow;
+    }
 
     fn is_connected(&self) -> bool {
-        let mut visited = 1u16;
+        let repunit = S::Row::repunit(N);
+        let mut visited = S::Row::ONE;
         for _ in 0..N {
             for irow in HotBitIter::from(visited) {
                 visited |= self.rows[irow];
             }
-            if visited == Self::REPUNIT {
+            if visited == repunit {
                 return true;
             }
         }
         false
     }
 
+    fn count_components(&self) -> usize {
+        let mut unvisited = S::Row::repunit(N);
+        let mut num_components = 0;
+        while unvisited != S::Row::ZERO {
+            let mut component = S::Row::ONE << unvisited.trailing_zeros() as usize;
+            loop {
+                let mut grown = component;
+                for irow in HotBitIter::from(component) {
+                    grown |= self.rows[irow];
+                }
+                if grown == component {
+                    break;
+                }
+                component = grown;
+            }
+            unvisited ^= component;
+            num_components += 1;
+        }
+        num_components
+    }
+
+    /// 独立な環の数 (辺の数 - 頂点数 + 連結成分の数). 辺を加えても減ることはない
+    fn count_rings(&self, num_edges: usize) -> usize {
+        num_edges + self.count_components() - N
+    }
+
+    /// 辺 (u, v) を通る最短の環の大きさ. 環に含まれない辺では None
+    fn shortest_ring_through(&self, u: usize, v: usize) -> Option<usize> {
+        let mut without_edge = *self;
+        without_edge.flip_at(u, v);
+        let target = S::Row::ONE << v;
+        let mut visited = S::Row::ONE << u;
+        let mut frontier = visited;
+        for dist in 1..N {
+            let mut next = S::Row::ZERO;
+            for i in HotBitIter::from(frontier) {
+                next |= without_edge.rows[i];
+            }
+            next &= !visited;
+            if next & target != S::Row::ZERO {
+                return Some(dist + 1);
+            }
+            if next == S::Row::ZERO {
+                return None;
+            }
+            visited |= next;
+            frontier = next;
+        }
+        None
+    }
+
+    /// 環に含まれる各辺について, その辺を通る最短の環の大きさを求め, 最小値と最大値を返す.
+    /// 最小値は内周 (girth) に等しい. 環がなければ None
+    fn ring_size_range(&self) -> Option<(usize, usize)> {
+        let mut range: Option<(usize, usize)> = None;
+        for u in 0..N {
+            for v in HotBitIter::from(self.rows[u]).filter(|&v| v > u) {
+                if let Some(size) = self.shortest_ring_through(u, v) {
+                    range = Some(match range {
+                        Some((min, max)) => (min.min(size), max.max(size)),
+                        None => (size, size),
+                    });
+                }
+            }
+        }
+        range
+    }
+
     const UNIT_MATRIX: [[u16; N]; N] = {
         let mut mat = [[0; N]; N];
         let mut i = 0;
@@ -60,8 +134,8 @@ impl<const N: usize> AdjacencyBitMatrix<N> {
         for step in 0..3 {
             mat = self * &mat;
 
-            for (irow, row) in mat.iter().enumerate() {
-                array_feat[irow][step] = {
+            for (irow, (feat, row)) in array_feat.iter_mut().zip(mat.iter()).enumerate() {
+                feat[step] = {
                     let sqsum: u16 = row.iter().map(|&x| x * x).sum();
                     (sqsum as u32) ^ (row[irow] as u32) << 16
                 }
@@ -93,12 +167,14 @@ impl<const N: usize> AdjacencyBitMatrix<N> {
     }
 }
 
-impl<const N: usize> std::ops::Mul<&[[u16; N]; N]> for &AdjacencyBitMatrix<N> {
+impl<const N: usize, S: Storage> std::ops::Mul<&[[u16; N]; N]> for &AdjacencyBitMatrix<N, S> {
     type Output = [[u16; N]; N];
 
+    // 隣接行列の積は, 隣接する頂点の行の和になる
+    #[allow(clippy::suspicious_arithmetic_impl)]
     fn mul(self, rhs: &[[u16; N]; N]) -> Self::Output {
         let mut out = [[0; N]; N];
-        for (out_row, self_row) in out.iter_mut().zip(self.rows.into_iter()) {
+        for (out_row, self_row) in out.iter_mut().zip(self.rows) {
             for j in HotBitIter::from(self_row) {
                 for (out_elem, &elem) in out_row.iter_mut().zip(rhs[j].iter()) {
                     *out_elem += elem;
@@ -109,23 +185,23 @@ impl<const N: usize> std::ops::Mul<&[[u16; N]; N]> for &AdjacencyBitMatrix<N> {
     }
 }
 
-impl<const N: usize> Permutable<N> for AdjacencyBitMatrix<N> {
+impl<const N: usize, S: Storage> Permutable<N> for AdjacencyBitMatrix<N, S> {
     fn permute_by(&self, perm: &[usize; N]) -> Self {
-        let mut rows = [0; N];
+        let mut rows = [S::Row::ZERO; N];
         for (irow_old, &irow_new) in perm.iter().enumerate() {
             let row_old = self.rows[irow_old];
             let row_new = &mut rows[irow_new];
 
             for icol_old in HotBitIter::from(row_old) {
                 let icol_new = perm[icol_old];
-                *row_new |= 1 << icol_new;
+                *row_new |= S::Row::ONE << icol_new;
             }
         }
         Self { rows }
     }
 }
 
-impl<const N: usize> std::fmt::Display for AdjacencyBitMatrix<N> {
+impl<const N: usize, S: Storage> std::fmt::Display for AdjacencyBitMatrix<N, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for irow in 0..N {
             for icol in 0..N {
@@ -150,13 +226,13 @@ impl<const N: usize> Features<N> {
         Self { raw }
     }
 
-    pub fn make_key(&self) -> u16 {
-        let mut store_key = 0;
+    pub fn make_key<K: Bits>(&self) -> K {
+        let mut store_key = K::ZERO;
         let mut prev = self.raw[0];
-        let mut bit = 0;
+        let mut bit = K::ZERO;
         for (i, &feat) in self.raw.iter().enumerate() {
             if feat != prev {
-                bit ^= 1;
+                bit ^= K::ONE;
                 prev = feat;
             }
             store_key |= bit << i;
@@ -165,36 +241,56 @@ impl<const N: usize> Features<N> {
     }
 
     pub fn is_monotonic(&self) -> bool {
-        self.make_key() == 0
+        self.raw.iter().all(|&feat| feat == self.raw[0])
     }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct AdjacencyTwoBitsMatrix<const N: usize> {
-    rows: [u32; N],
+pub struct AdjacencyTwoBitsMatrix<const N: usize, S: Storage = Storage16> {
+    rows: [S::TwoBitsRow; N],
 }
 
-impl<const N: usize> AdjacencyTwoBitsMatrix<N> {
+impl<const N: usize, S: Storage> AdjacencyTwoBitsMatrix<N, S> {
+    /// 結合を1つも持たない行列
+    pub fn empty() -> Self {
+        Self {
+            rows: [S::TwoBitsRow::ZERO; N],
+        }
+    }
+
     pub fn element_at(&self, irow: usize, icol: usize) -> u32 {
-        self.rows[irow] >> (2 * icol) & 0b11
+        (self.rows[irow] >> (2 * icol)).low_u32() & 0b11
     }
 
     pub fn increment_at(&mut self, irow: usize, icol: usize) {
-        self.rows[irow] += 1 << (2 * icol);
-        self.rows[icol] += 1 << (2 * irow);
+        self.rows[irow] += S::TwoBitsRow::ONE << (2 * icol);
+        self.rows[icol] += S::TwoBitsRow::ONE << (2 * irow);
     }
 
     pub fn degree_of(&self, idx: usize) -> u32 {
         let row = self.rows[idx];
-        let even_bits = row & 0x5555_5555; // 0b0101...0101
-        let odd_bits = row & 0xaaaa_aaaa; // 0b1010...1010
+        let even_bits = row & S::TwoBitsRow::EVEN_BITS; // 0b0101...0101
+        let odd_bits = row & !S::TwoBitsRow::EVEN_BITS; // 0b1010...1010
         even_bits.count_ones() + 2 * odd_bits.count_ones()
     }
+
+    /// 原子 `idx` から出る二重結合以上の結合の数. 次数 2, 3 はともに上位ビットが立つ
+    pub fn count_multiple_bonds_of(&self, idx: usize) -> u32 {
+        (self.rows[idx] & !S::TwoBitsRow::EVEN_BITS).count_ones()
+    }
+
+    pub fn count_hydrogen(&self) -> usize {
+        let mut degrees_sum = 0;
+        for i in 0..N {
+            degrees_sum += self.degree_of(i) as usize;
+        }
+        4 * N - degrees_sum
+    }
 }
 
-impl<const N: usize> Permutable<N> for AdjacencyTwoBitsMatrix<N> {
+impl<const N: usize, S: Storage> Permutable<N> for AdjacencyTwoBitsMatrix<N, S> {
     fn permute_by(&self, perm: &[usize; N]) -> Self {
-        let mut rows = [0; N];
+        let mut rows = [S::TwoBitsRow::ZERO; N];
         for (irow_old, &irow_new) in perm.iter().enumerate() {
             let row_old = self.rows[irow_old];
             let row_new = &mut rows[irow_new];
@@ -203,26 +299,26 @@ impl<const N: usize> Permutable<N> for AdjacencyTwoBitsMatrix<N> {
                 let icol_old = num_zeros_old / 2;
                 let icol_new = perm[icol_old];
                 let num_zeros_new = icol_new * 2 + num_zeros_old % 2;
-                *row_new |= 1 << num_zeros_new;
+                *row_new |= S::TwoBitsRow::ONE << num_zeros_new;
             }
         }
         Self { rows }
     }
 }
 
-impl<const N: usize> From<AdjacencyBitMatrix<N>> for AdjacencyTwoBitsMatrix<N> {
-    fn from(mat: AdjacencyBitMatrix<N>) -> Self {
-        let mut rows = [0; N];
-        for (row_2, row_1) in rows.iter_mut().zip(mat.rows.into_iter()) {
+impl<const N: usize, S: Storage> From<AdjacencyBitMatrix<N, S>> for AdjacencyTwoBitsMatrix<N, S> {
+    fn from(mat: AdjacencyBitMatrix<N, S>) -> Self {
+        let mut rows = [S::TwoBitsRow::ZERO; N];
+        for (row_2, row_1) in rows.iter_mut().zip(mat.rows) {
             for icol in HotBitIter::from(row_1) {
-                *row_2 |= 1 << (2 * icol);
+                *row_2 |= S::TwoBitsRow::ONE << (2 * icol);
             }
         }
         Self { rows }
     }
 }
 
-impl<const N: usize> std::fmt::Display for AdjacencyTwoBitsMatrix<N> {
+impl<const N: usize, S: Storage> std::fmt::Display for AdjacencyTwoBitsMatrix<N, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         for irow in 0..N {
             for icol in 0..N {
@@ -293,20 +389,62 @@ impl<const N: usize> Cursor<N> {
     }
 }
 
-pub struct RedundantSaturatedHydrocarbonIter<const N: usize> {
-    current: AdjacencyBitMatrix<N>,
+/// RedundantSaturatedHydrocarbonIter が生成する骨格に課す制約
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkeletonConstraints {
+    /// 骨格に含まれる独立な環の数の上限. 辺を加えても環は減らないので, 超えた時点で探索を打ち切る
+    pub max_rings: usize,
+    /// 骨格に含まれる独立な環の数の下限. 完成した骨格でのみ判定する
+    pub min_rings: usize,
+    /// 環の大きさの下限. 辺を加えると環は小さくなる一方なので, 下回った時点で探索を打ち切る
+    pub min_ring_size: usize,
+    /// 環の大きさの上限. 辺を加えると満たすようになりうるので, 完成した骨格でのみ判定する
+    pub max_ring_size: usize,
+}
+
+impl Default for SkeletonConstraints {
+    fn default() -> Self {
+        Self {
+            max_rings: usize::MAX,
+            min_rings: 0,
+            min_ring_size: 0,
+            max_ring_size: usize::MAX,
+        }
+    }
+}
+
+impl SkeletonConstraints {
+    // 最小の環は三員環なので, 下限が 3 以下なら判定は不要
+    fn has_min_ring_size(&self) -> bool {
+        self.min_ring_size > 3
+    }
+
+    fn has_max_ring_size(&self) -> bool {
+        self.max_ring_size != usize::MAX
+    }
+}
+
+pub struct RedundantSaturatedHydrocarbonIter<const N: usize, S: Storage = Storage16> {
+    current: AdjacencyBitMatrix<N, S>,
     cursor: Cursor<N>,
+    num_edges: usize,
+    constraints: SkeletonConstraints,
 }
 
-impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
-    pub fn new() -> Self {
+impl<const N: usize, S: Storage> RedundantSaturatedHydrocarbonIter<N, S> {
+    pub fn new(constraints: SkeletonConstraints) -> Self {
         Self {
             current: AdjacencyBitMatrix::ZERO,
             cursor: Cursor::new(0, 1),
+            num_edges: 0,
+            constraints,
         }
     }
 
-    pub fn new_with_fixed_digits(fixed_digits: usize) -> Vec<Self> {
+    pub fn new_with_fixed_digits(
+        fixed_digits: usize,
+        constraints: SkeletonConstraints,
+    ) -> Vec<Self> {
         // N * (N - 1) / 2 以上になると桁があふれるのでNG
         // N * (N - 1) / 2 - 1 以上になると, 開始時のカーソルの位置が最後の桁となり,
         // 「1回目は skip する」という仕様により壊れてしまうのでNG
@@ -315,7 +453,7 @@ impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
         let mut iters = Vec::new();
 
         for bits in 0..(1u32 << fixed_digits) {
-            let mut iter = Self::new();
+            let mut iter = Self::new(constraints);
             for i in 0..fixed_digits {
                 if bits & (1 << i) != 0 {
                     iter.flip_at_cursor();
@@ -338,20 +476,29 @@ impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
         iters
     }
 
-    fn element_at_cursor(&self) -> u16 {
+    fn element_at_cursor(&self) -> S::Row {
         self.current.element_at(self.cursor.irow, self.cursor.icol)
     }
 
     fn flip_at_cursor(&mut self) {
         self.current.flip_at(self.cursor.irow, self.cursor.icol);
+        if self.element_at_cursor() == S::Row::ZERO {
+            self.num_edges -= 1;
+        } else {
+            self.num_edges += 1;
+        }
+    }
+
+    fn calc_fat_row(row: S::Row) -> <S::Row as Bits>::Fat {
+        S::Row::fat(row.count_ones(), row)
     }
 
-    fn calc_fat_row(row: u16) -> u32 {
-        row.count_ones() << 16 | row as u32
+    fn max_fat_row() -> <S::Row as Bits>::Fat {
+        S::Row::fat(4, S::Row::MAX)
     }
 
     fn is_fine_up_to_cursor(&self) -> bool {
-        let mut prev_fat_row = 0x0004_ffff; // 直前の確定済みの fat_row
+        let mut prev_fat_row = Self::max_fat_row(); // 直前の確定済みの fat_row
         for (i, &row) in self.current.rows.iter().enumerate() {
             let fat_row = Self::calc_fat_row(row);
             if fat_row > prev_fat_row {
@@ -365,13 +512,29 @@ impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
     }
 
     fn check_current(&mut self) -> bool {
+        // カーソルより後ろの桁を立てても環は減らないので, 以降の探索ごと枝刈りできる.
+        // 環の数は辺の数を超えないので, その場合は連結成分を数えない
+        if self.num_edges > self.constraints.max_rings
+            && self.current.count_rings(self.num_edges) > self.constraints.max_rings
+        {
+            return false;
+        }
+        if self.constraints.has_min_ring_size()
+            && self
+                .current
+                .ring_size_range()
+                .is_some_and(|(min, _)| min < self.constraints.min_ring_size)
+        {
+            return false;
+        }
+
         let prev_fat_row = if self.cursor.irow == 0 {
-            0x0004_ffff
+            Self::max_fat_row()
         } else {
             Self::calc_fat_row(self.current.rows[self.cursor.irow - 1])
         };
 
-        let mut fat_rows = [0; N];
+        let mut fat_rows = [Self::calc_fat_row(S::Row::ZERO); N];
         for (i, &row) in self.current.rows.iter().enumerate().skip(self.cursor.irow) {
             let fat_row = Self::calc_fat_row(row);
             if fat_row > prev_fat_row {
@@ -390,10 +553,26 @@ impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
             max_fat_row = max_fat_row.max(fat_row);
         }
         self.cursor.move_terminal(irow_bad_min.min(N - 2));
-        irow_bad_min == N && self.current.is_connected()
+        irow_bad_min == N
+            && self.satisfies_min_rings()
+            && self.current.is_connected()
+            && self.satisfies_max_ring_size()
+    }
+
+    // 連結な骨格の環の数は 辺の数 - N + 1
+    fn satisfies_min_rings(&self) -> bool {
+        self.num_edges + 1 >= N + self.constraints.min_rings
     }
 
-    fn next_raw(&mut self) -> Option<AdjacencyBitMatrix<N>> {
+    fn satisfies_max_ring_size(&self) -> bool {
+        !self.constraints.has_max_ring_size()
+            || self
+                .current
+                .ring_size_range()
+                .is_none_or(|(_, max)| max <= self.constraints.max_ring_size)
+    }
+
+    fn next_raw(&mut self) -> Option<AdjacencyBitMatrix<N, S>> {
         let mut forward = !self.cursor.is_at_last();
         loop {
             if forward {
@@ -404,7 +583,7 @@ impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
                 }
             } else {
                 self.flip_at_cursor();
-                if self.element_at_cursor() == 0 {
+                if self.element_at_cursor() == S::Row::ZERO {
                     if self.cursor.is_at_first() {
                         return None;
                     }
@@ -417,8 +596,8 @@ impl<const N: usize> RedundantSaturatedHydrocarbonIter<N> {
     }
 }
 
-impl<const N: usize> Iterator for RedundantSaturatedHydrocarbonIter<N> {
-    type Item = (AdjacencyBitMatrix<N>, Features<N>);
+impl<const N: usize, S: Storage> Iterator for RedundantSaturatedHydrocarbonIter<N, S> {
+    type Item = (AdjacencyBitMatrix<N, S>, Features<N>);
 
     fn next(&mut self) -> Option<Self::Item> {
         let raw = self.next_raw()?;
@@ -426,3 +605,153 @@ impl<const N: usize> Iterator for RedundantSaturatedHydrocarbonIter<N> {
         Some((canonical, feat))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            enumeration::{count_isomers, EnumerationConfig},
+            storage::{Carbon, SelectStorage},
+            testing::{assert_same_counts_as_filter, count_edges},
+        },
+        *,
+    };
+
+    // 鎖状のみの列挙が, 全列挙を辺の数 N - 1 で絞り込んだ結果と一致することを確かめる
+    fn assert_acyclic_counts<const N: usize>() -> Vec<usize>
+    where
+        Carbon<N>: SelectStorage,
+    {
+        assert_same_counts_as_filter::<N>(
+            |h| count_edges(h) == N - 1,
+            |config| config.acyclic = true,
+            "acyclic",
+        )
+    }
+
+    #[test]
+    fn acyclic() {
+        assert_acyclic_counts::<2>();
+        assert_acyclic_counts::<3>();
+        assert_acyclic_counts::<4>();
+        assert_acyclic_counts::<5>();
+        assert_acyclic_counts::<7>();
+        assert_acyclic_counts::<8>();
+
+        // 鎖状の C6: アルカン 5, アルケン 13, アルキン 7 とジエン 16
+        let counts = assert_acyclic_counts::<6>();
+        assert_eq!(counts[14], 5);
+        assert_eq!(counts[12], 13);
+        assert_eq!(counts[10], 23);
+    }
+
+    // 環の数の制約が, 全列挙を辺の数で絞り込んだ結果と一致することを確かめる
+    fn assert_ring_counts<const N: usize>(max: Option<usize>, exact: Option<usize>) -> Vec<usize>
+    where
+        Carbon<N>: SelectStorage,
+    {
+        assert_same_counts_as_filter::<N>(
+            move |h| {
+                let num_rings = count_edges(h) + 1 - N;
+                max.is_none_or(|max| num_rings <= max)
+                    && exact.is_none_or(|exact| num_rings == exact)
+            },
+            |config| {
+                config.max_rings = max;
+                config.exact_rings = exact;
+            },
+            &format!("max = {max:?}, exact = {exact:?}"),
+        )
+    }
+
+    #[test]
+    fn ring_counts() {
+        for (max, exact) in [(Some(1), None), (None, Some(2)), (Some(3), Some(1))] {
+            assert_ring_counts::<4>(max, exact);
+            assert_ring_counts::<5>(max, exact);
+            assert_ring_counts::<6>(max, exact);
+            assert_ring_counts::<7>(max, exact);
+        }
+
+        // 単環の C5H10 はシクロペンタン, メチルシクロブタン, エチルシクロプロパン,
+        // 1,1- と 1,2-ジメチルシクロプロパンの 5 つ. C5H8 では, これらの骨格に
+        // 二重結合を置く位置がそれぞれ 1, 3, 4, 1, 3 通りある
+        let counts = assert_ring_counts::<5>(None, Some(1));
+        assert_eq!(counts[10], 5);
+        assert_eq!(counts[8], 12);
+    }
+
+    // 結合ごとに, その結合を通る最短の環の大きさを幅優先探索で求める
+    fn ring_sizes<const N: usize, S: Storage>(
+        hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+    ) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        for u in 0..N {
+            for v in (u + 1)..N {
+                if hydrocarbon.element_at(u, v) == 0 {
+                    continue;
+                }
+                let mut dist = [usize::MAX; N];
+                dist[u] = 0;
+                let mut queue = std::collections::VecDeque::from([u]);
+                while let Some(i) = queue.pop_front() {
+                    for j in 0..N {
+                        let is_removed = (i, j) == (u, v) || (i, j) == (v, u);
+                        if hydrocarbon.element_at(i, j) != 0 && !is_removed && dist[j] == usize::MAX
+                        {
+                            dist[j] = dist[i] + 1;
+                            queue.push_back(j);
+                        }
+                    }
+                }
+                if dist[v] != usize::MAX {
+                    sizes.push(dist[v] + 1);
+                }
+            }
+        }
+        sizes
+    }
+
+    // 環の大きさの制約が, 全列挙を同じ条件で絞り込んだ結果と一致することを確かめる
+    fn assert_ring_size_counts<const N: usize>(min: Option<usize>, max: Option<usize>)
+    where
+        Carbon<N>: SelectStorage,
+    {
+        assert_same_counts_as_filter::<N>(
+            move |h| {
+                ring_sizes(h).into_iter().all(|size| {
+                    min.is_none_or(|min| size >= min) && max.is_none_or(|max| size <= max)
+                })
+            },
+            |config| {
+                config.min_ring_size = min;
+                config.max_ring_size = max;
+            },
+            &format!("min = {min:?}, max = {max:?}"),
+        );
+    }
+
+    #[test]
+    fn ring_sizes_constraints() {
+        for (min, max) in [(Some(4), None), (None, Some(5)), (Some(5), Some(6))] {
+            assert_ring_size_counts::<4>(min, max);
+            assert_ring_size_counts::<5>(min, max);
+            assert_ring_size_counts::<6>(min, max);
+            assert_ring_size_counts::<7>(min, max);
+        }
+
+        // 6員環を1つだけ持つ C6 はシクロヘキサン骨格のみ. C6H8 はシクロヘキサジエン 3 種
+        // (1,2-, 1,3-, 1,4-) とシクロヘキシン, C6H6 はベンゼン, 1,2,3- と 1,2,4-シクロヘキサトリエン,
+        // 1,3- と 1,4-シクロヘキセンイン
+        let config = EnumerationConfig {
+            exact_rings: Some(1),
+            min_ring_size: Some(6),
+            ..Default::default()
+        };
+        let counts = count_isomers::<6>(config).unwrap();
+        assert_eq!(counts[12], 1);
+        assert_eq!(counts[10], 1);
+        assert_eq!(counts[8], 4);
+        assert_eq!(counts[6], 5);
+    }
+}
@@ -1,29 +1,72 @@
-use std::{sync::mpsc, thread};
+use std::{
+    num::NonZeroUsize,
+    sync::{mpsc, Mutex, PoisonError},
+    thread,
+};
 
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use super::{
-    dehydrogenation::generate_dehydrogenated,
+    aromaticity::{collapse_resonance, resonance_automorphisms},
+    dehydrogenation::{
+        count_dehydrogenated, generate_dehydrogenated, generate_dehydrogenated_at, max_degrees,
+    },
+    element::{assign_elements, Formula, Molecule},
+    error::EnumerationError,
     matrix::{
         AdjacencyBitMatrix, AdjacencyTwoBitsMatrix, Features, RedundantSaturatedHydrocarbonIter,
+        SkeletonConstraints,
     },
     permutation::{calc_orbit_stabilizer, Permutation, PermutationStore},
+    plausibility::{has_cumulated_double_bonds, PlausibilityRules},
+    sink::IsomerSink,
+    species::{assign_open_valences, Species},
+    storage::{Storage, StorageOf},
 };
 
-pub fn create_feat2skeletons<const N: usize>(
+pub type Filter<const N: usize, S = StorageOf<N>> =
+    dyn Fn(&AdjacencyTwoBitsMatrix<N, S>) -> bool + Send + Sync;
+
+// 脱水素化の結果を受け渡すチャネルの容量. sink の処理が遅い場合にメモリを圧迫しないよう制限する
+const CHANNEL_CAPACITY: usize = 1024;
+
+// 軌道の事前確保量の上限. (N - 1)!/2 は N = 10 で 1 << 16 を超え, 作業スレッドごとに確保されるため,
+// それより大きな軌道では表を伸長させる
+const MAX_ORBIT_PRE_ALLOC: usize = 1 << 16;
+
+/// スレッド数を指定しない場合に使うスレッド数. 取得できなければ 1
+pub fn default_num_threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+pub fn create_feat2skeletons<const N: usize, S: Storage>(
     fixed_digits: usize,
-) -> FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N>>> {
-    let skeleton_iters = RedundantSaturatedHydrocarbonIter::new_with_fixed_digits(fixed_digits);
+    constraints: SkeletonConstraints,
+    num_threads: Option<usize>,
+) -> Result<FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>, EnumerationError> {
+    let skeleton_iters =
+        RedundantSaturatedHydrocarbonIter::new_with_fixed_digits(fixed_digits, constraints);
+    let num_threads = num_threads
+        .unwrap_or_else(default_num_threads)
+        .min(skeleton_iters.len());
+    let jobs = Mutex::new(skeleton_iters.into_iter());
     let (sender, receiver) = mpsc::channel();
     let mut result = FxHashMap::default();
 
     thread::scope(|s| {
-        for iter in skeleton_iters {
+        let mut handles = Vec::with_capacity(num_threads);
+        for _ in 0..num_threads {
             let sender = mpsc::Sender::clone(&sender);
-            s.spawn(move || {
-                let v: Vec<_> = iter.collect();
-                sender.send(v).unwrap();
-            });
+            let jobs = &jobs;
+            handles.push(s.spawn(move || {
+                while let Some(iter) = next_job(jobs) {
+                    let v: Vec<_> = iter.collect();
+                    sender
+                        .send(v)
+                        .map_err(|_| EnumerationError::WorkerDisconnected)?;
+                }
+                Ok(())
+            }));
         }
         drop(sender); // 最初の1個が余るので手動で drop
 
@@ -32,84 +75,553 @@ pub fn create_feat2skeletons<const N: usize>(
                 result.entry(feat).or_insert_with(Vec::new).push(skeleton);
             }
         }
-    });
+        join_all(handles)
+    })?;
 
-    result
+    Ok(result)
+}
+
+/// 脱水素化の段階で適用する条件
+pub struct DehydrogenationOptions<'a, const N: usize, S: Storage> {
+    /// 全てが `true` を返した炭化水素のみを結果に含める
+    pub filters: &'a [Box<Filter<N, S>>],
+    /// 指定した場合, 水素数がこの値に一致する炭化水素のみを生成する
+    pub num_hydrogens: Option<usize>,
+    /// 不飽和結合を置く際に従う規則
+    pub plausibility: PlausibilityRules,
+    /// 芳香環の Kekulé 構造が異なるだけの炭化水素を1つにまとめる
+    pub collapse_resonance: bool,
+}
+
+impl<const N: usize, S: Storage> DehydrogenationOptions<'_, N, S> {
+    // 水素数 `num_h_skeleton` の骨格から水素数を `num_hydrogens` にするのに必要な結合の追加数.
+    // 到達不能なら `None`
+    fn target_depth(&self, num_h_skeleton: usize) -> Option<Option<usize>> {
+        let Some(num_h) = self.num_hydrogens else {
+            return Some(None);
+        };
+        let diff = num_h_skeleton.checked_sub(num_h)?;
+        (diff % 2 == 0).then_some(Some(diff / 2))
+    }
+
+    fn accepts(&self, hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>) -> bool {
+        self.filters.iter().all(|f| f(hydrocarbon))
+    }
+
+    // 骨格を脱水素化し, 水素数とフィルタの条件を満たす炭化水素を返す.
+    // Kekulé 構造をまとめる場合は, フィルタを通ったもののうち最初の構造を残す
+    fn dehydrogenate(
+        &self,
+        skeleton: AdjacencyTwoBitsMatrix<N, S>,
+        stabilizer: &[Permutation<N>],
+    ) -> Vec<AdjacencyTwoBitsMatrix<N, S>> {
+        let mut dehydrogenated = self.dehydrogenate_with_valences(skeleton, stabilizer, [4; N]);
+        if self.collapse_resonance {
+            collapse_resonance(&skeleton, stabilizer, &mut dehydrogenated);
+        }
+        dehydrogenated
+    }
+
+    // 各頂点の原子価が `valences` の骨格を脱水素化し, 水素数とフィルタの条件を満たすものを返す
+    fn dehydrogenate_with_valences(
+        &self,
+        skeleton: AdjacencyTwoBitsMatrix<N, S>,
+        stabilizer: &[Permutation<N>],
+        valences: [u32; N],
+    ) -> Vec<AdjacencyTwoBitsMatrix<N, S>> {
+        // 骨格の水素数は, 原子価の和から結合の数の 2 倍を引いたもの
+        let num_h_skeleton = valences.iter().sum::<u32>() as usize
+            - (0..N)
+                .map(|i| skeleton.degree_of(i) as usize)
+                .sum::<usize>();
+        let max_degrees = max_degrees(valences);
+        let rules = &self.plausibility;
+        let mut dehydrogenated = match self.target_depth(num_h_skeleton) {
+            Some(None) => generate_dehydrogenated(skeleton, stabilizer, rules, &max_degrees),
+            Some(Some(depth)) => {
+                generate_dehydrogenated_at(skeleton, stabilizer, rules, &max_degrees, depth)
+            }
+            None => Vec::new(),
+        };
+        dehydrogenated.retain(|h| self.accepts(h));
+        dehydrogenated
+    }
+}
+
+pub fn dehydrogenate_feat2skeletons<const N: usize, S: Storage>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
+    max_num_feats: usize,
+    num_threads: Option<usize>,
+    options: &DehydrogenationOptions<N, S>,
+    mut sink: impl IsomerSink<N, S>,
+) -> Result<(), EnumerationError> {
+    process_feat2skeletons(
+        feat2skeletons,
+        perm_store,
+        max_num_feats,
+        num_threads,
+        |skeleton, stabilizer| options.dehydrogenate(skeleton.into(), stabilizer),
+        |dehydrogenated| {
+            dehydrogenated
+                .into_iter()
+                .try_for_each(|hydrocarbon| sink.push(hydrocarbon))
+        },
+    )
+}
+
+/// 脱水素化した炭化水素を保持せず, 水素数ごとの個数のみを集計する.
+/// `count_excluded` が真なら累積二重結合の規則で打ち切る枝も同じ探索で辿り,
+/// 規則で除かれた個数を2つ目の返り値に数える. 偽なら2つ目は全て 0 になる
+pub fn count_feat2skeletons<const N: usize, S: Storage>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
+    max_num_feats: usize,
+    num_threads: Option<usize>,
+    options: &DehydrogenationOptions<N, S>,
+    count_excluded: bool,
+) -> Result<(Vec<usize>, Vec<usize>), EnumerationError> {
+    // 累積二重結合を持つ炭化水素の子孫は全て累積二重結合を持つので,
+    // 規則を外して探索し, 見つかった炭化水素を判定して振り分ければよい
+    let count_excluded = count_excluded && options.plausibility.no_cumulated_double_bonds;
+    let options = DehydrogenationOptions {
+        plausibility: PlausibilityRules {
+            no_cumulated_double_bonds: options.plausibility.no_cumulated_double_bonds
+                && !count_excluded,
+            ..options.plausibility
+        },
+        ..*options
+    };
+    let is_excluded = |hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>| {
+        count_excluded && has_cumulated_double_bonds(hydrocarbon)
+    };
+
+    let mut counts = vec![0; 2 * N + 3];
+    let mut excluded = vec![0; 2 * N + 3];
+    process_feat2skeletons(
+        feat2skeletons,
+        perm_store,
+        max_num_feats,
+        num_threads,
+        |skeleton, stabilizer| {
+            let skeleton = AdjacencyTwoBitsMatrix::from(skeleton);
+            let mut counts_by_h = vec![0; 2 * N + 3];
+            let mut excluded_by_h = vec![0; 2 * N + 3];
+            let Some(target_depth) = options.target_depth(skeleton.count_hydrogen()) else {
+                return (counts_by_h, excluded_by_h);
+            };
+            if options.filters.is_empty() && !options.collapse_resonance {
+                // 脱水素化の段数 = 追加した結合の数 なので, 段ごとに水素が 2 個ずつ減る
+                let num_h_skeleton = skeleton.count_hydrogen();
+                let max_depth = target_depth.unwrap_or(usize::MAX);
+                let (counts_by_level, excluded_by_level) = count_dehydrogenated(
+                    skeleton,
+                    stabilizer,
+                    &options.plausibility,
+                    &max_degrees([4; N]),
+                    max_depth,
+                    is_excluded,
+                );
+                for (level, (count, num_excluded)) in counts_by_level
+                    .into_iter()
+                    .zip(excluded_by_level)
+                    .enumerate()
+                {
+                    if target_depth.is_none_or(|depth| depth == level) {
+                        counts_by_h[num_h_skeleton - 2 * level] += count;
+                        excluded_by_h[num_h_skeleton - 2 * level] += num_excluded;
+                    }
+                }
+            } else {
+                for hydrocarbon in options.dehydrogenate(skeleton, stabilizer) {
+                    if is_excluded(&hydrocarbon) {
+                        excluded_by_h[hydrocarbon.count_hydrogen()] += 1;
+                    } else {
+                        counts_by_h[hydrocarbon.count_hydrogen()] += 1;
+                    }
+                }
+            }
+            (counts_by_h, excluded_by_h)
+        },
+        |(counts_by_h, excluded_by_h)| {
+            for (total, count) in counts.iter_mut().zip(counts_by_h) {
+                *total += count;
+            }
+            for (total, count) in excluded.iter_mut().zip(excluded_by_h) {
+                *total += count;
+            }
+            Ok(())
+        },
+    )?;
+    Ok((counts, excluded))
+}
+
+/// 脱水素化した炭化水素ごとに, 骨格の固定部分群から自己同型群の生成元を求めて `analyze` を適用し,
+/// 炭化水素とその解析結果を `consume` に渡す.
+/// `analyze` には結合次数を保つ自己同型群と, 対称性の解析に用いる自己同型群の生成元を渡す.
+/// 後者は `collapse_resonance` が有効なら芳香環の Kekulé 構造を区別せず, 無効なら前者に等しい
+pub fn analyze_feat2skeletons<const N: usize, S: Storage, T: Send>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
+    max_num_feats: usize,
+    num_threads: Option<usize>,
+    options: &DehydrogenationOptions<N, S>,
+    analyze: impl Fn(&AdjacencyTwoBitsMatrix<N, S>, &[Permutation<N>], &[Permutation<N>]) -> T + Sync,
+    mut consume: impl FnMut(AdjacencyTwoBitsMatrix<N, S>, T) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError> {
+    process_feat2skeletons(
+        feat2skeletons,
+        perm_store,
+        max_num_feats,
+        num_threads,
+        |skeleton, stabilizer| {
+            let skeleton = AdjacencyTwoBitsMatrix::from(skeleton);
+            let dehydrogenated = options.dehydrogenate(skeleton, stabilizer);
+            dehydrogenated
+                .into_iter()
+                .map(|hydrocarbon| {
+                    // 炭化水素の自己同型は骨格の自己同型のうち結合次数を保つもの.
+                    // Kekulé 構造をまとめる場合は, 対称性の解析では芳香環の結合次数を区別しない.
+                    // 立体単位は結合次数で決まるので, その解析には結合次数を保つものを用いる
+                    let automorphisms = calc_orbit_stabilizer(hydrocarbon, stabilizer, 0).1;
+                    let analysis = if options.collapse_resonance {
+                        let resonance =
+                            resonance_automorphisms(&skeleton, &hydrocarbon, stabilizer);
+                        analyze(&hydrocarbon, &automorphisms, &resonance)
+                    } else {
+                        analyze(&hydrocarbon, &automorphisms, &automorphisms)
+                    };
+                    (hydrocarbon, analysis)
+                })
+                .collect::<Vec<_>>()
+        },
+        |analyzed| {
+            analyzed
+                .into_iter()
+                .try_for_each(|(hydrocarbon, analysis)| consume(hydrocarbon, analysis))
+        },
+    )
+}
+
+/// 重複を除いた骨格を, 脱水素化せずに `consume` に渡す
+pub fn deduplicate_feat2skeletons<const N: usize, S: Storage>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
+    max_num_feats: usize,
+    num_threads: Option<usize>,
+    consume: impl FnMut(AdjacencyBitMatrix<N, S>) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError> {
+    process_feat2skeletons(
+        feat2skeletons,
+        perm_store,
+        max_num_feats,
+        num_threads,
+        |skeleton, _| skeleton,
+        consume,
+    )
+}
+
+/// 骨格の頂点に分子式の元素を割り当ててから脱水素化し, 分子式に一致する分子を `sink` に渡す.
+/// `options.num_hydrogens` は無視し, 分子式の水素数を用いる
+pub fn dehydrogenate_colored_feat2skeletons<const N: usize, S: Storage>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
+    max_num_feats: usize,
+    num_threads: Option<usize>,
+    options: &DehydrogenationOptions<N, S>,
+    formula: &Formula,
+    mut sink: impl FnMut(Molecule<N, S>) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError> {
+    let options = DehydrogenationOptions {
+        num_hydrogens: Some(formula.num_hydrogens()),
+        ..*options
+    };
+    process_feat2skeletons(
+        feat2skeletons,
+        perm_store,
+        max_num_feats,
+        num_threads,
+        |skeleton, stabilizer| {
+            let skeleton = AdjacencyTwoBitsMatrix::from(skeleton);
+            let mut molecules = Vec::new();
+            for (elements, stabilizer) in assign_elements(&skeleton, stabilizer, formula) {
+                let valences = elements.map(|e| e.valence());
+                let dehydrogenated =
+                    options.dehydrogenate_with_valences(skeleton, &stabilizer, valences);
+                molecules.extend(
+                    dehydrogenated
+                        .into_iter()
+                        .map(|bonds| Molecule { elements, bonds }),
+                );
+            }
+            molecules
+        },
+        |molecules| molecules.into_iter().try_for_each(&mut sink),
+    )
+}
+
+/// 骨格の炭素に `num_open_valences` 個の開いた原子価を割り振ってから脱水素化し,
+/// 水素数とフィルタの条件を満たす化学種を `sink` に渡す
+pub fn dehydrogenate_open_feat2skeletons<const N: usize, S: Storage>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
+    max_num_feats: usize,
+    num_threads: Option<usize>,
+    options: &DehydrogenationOptions<N, S>,
+    num_open_valences: usize,
+    mut sink: impl FnMut(Species<N, S>) -> Result<(), EnumerationError>,
+) -> Result<(), EnumerationError> {
+    process_feat2skeletons(
+        feat2skeletons,
+        perm_store,
+        max_num_feats,
+        num_threads,
+        |skeleton, stabilizer| {
+            let skeleton = AdjacencyTwoBitsMatrix::from(skeleton);
+            let mut species = Vec::new();
+            for (open_valences, stabilizer) in
+                assign_open_valences(&skeleton, stabilizer, num_open_valences)
+            {
+                let valences = open_valences.map(|open| 4 - open);
+                let dehydrogenated =
+                    options.dehydrogenate_with_valences(skeleton, &stabilizer, valences);
+                species.extend(dehydrogenated.into_iter().map(|bonds| Species {
+                    open_valences,
+                    bonds,
+                }));
+            }
+            species
+        },
+        |species| species.into_iter().try_for_each(&mut sink),
+    )
 }
 
-pub fn dehydrogenate_feat2skeletons<const N: usize>(
-    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N>>>,
-    perm_store: &PermutationStore<N>,
+/// 重複を除いた骨格とその固定部分群それぞれに `process` を適用し,
+/// その結果を呼び出し元のスレッドで `consume` に渡す
+fn process_feat2skeletons<const N: usize, S, T, P, C>(
+    feat2skeletons: FxHashMap<Features<N>, Vec<AdjacencyBitMatrix<N, S>>>,
+    perm_store: &PermutationStore<N, S>,
     max_num_feats: usize,
-) -> Vec<AdjacencyTwoBitsMatrix<N>> {
+    num_threads: Option<usize>,
+    process: P,
+    mut consume: C,
+) -> Result<(), EnumerationError>
+where
+    S: Storage,
+    T: Send,
+    P: Fn(AdjacencyBitMatrix<N, S>, &[Permutation<N>]) -> T + Sync,
+    C: FnMut(T) -> Result<(), EnumerationError>,
+{
     let f2s = &feat2skeletons;
-    let num_threads = f2s.len().div_ceil(max_num_feats);
+    let num_chunks = f2s.len().div_ceil(max_num_feats);
 
-    let mut hydrocarbons = Vec::new();
-    let (sender, receiver) = mpsc::channel();
+    // 特徴量が単調なケースは重いため, 個々のジョブとして処理する
+    let mut jobs: Vec<_> = f2s
+        .iter()
+        .filter(|(feat, _)| feat.is_monotonic())
+        .map(|(&feat, skeletons)| Job::Monotonic(feat, skeletons))
+        .collect();
+    jobs.extend((0..num_chunks).map(Job::Chunk));
+    let num_threads = num_threads
+        .unwrap_or_else(default_num_threads)
+        .min(jobs.len());
+    let jobs = Mutex::new(jobs.into_iter());
+
+    let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
 
-    let pre_alloc: usize = (3..N).product(); // (N - 1)!/2
+    let pre_alloc = (3..N)
+        .try_fold(1usize, |acc, x| acc.checked_mul(x)) // (N - 1)!/2
+        .map_or(MAX_ORBIT_PRE_ALLOC, |x| x.min(MAX_ORBIT_PRE_ALLOC));
+    let process = &process;
     thread::scope(|s| {
-        // 特徴量が単調なケース: 重いため個々のスレッドで処理する
-        for (&feat, skeletons) in f2s.iter() {
-            if !feat.is_monotonic() {
-                continue;
-            }
-            let sender = mpsc::Sender::clone(&sender);
-            s.spawn(move || {
-                let skeletons_stabilizers =
-                    remove_duplicates(skeletons.iter().copied(), feat, perm_store, pre_alloc);
-                for (skeleton, stabilizer) in skeletons_stabilizers {
-                    let dehydrogenated = generate_dehydrogenated(skeleton.into(), &stabilizer);
-                    sender.send(dehydrogenated).unwrap();
-                }
-            });
-        }
+        let mut handles = Vec::with_capacity(num_threads);
+        for _ in 0..num_threads {
+            let sender = mpsc::SyncSender::clone(&sender);
+            let jobs = &jobs;
+            handles.push(s.spawn(move || {
+                let send = |skeleton: AdjacencyBitMatrix<N, S>, stabilizer: &[Permutation<N>]| {
+                    sender
+                        .send(process(skeleton, stabilizer))
+                        .map_err(|_| EnumerationError::WorkerDisconnected)
+                };
 
-        // 特徴量が単調でないケース
-        for ith in 0..num_threads {
-            let sender = mpsc::Sender::clone(&sender);
-            s.spawn(move || {
-                for (&feat, skeletons) in f2s.iter().skip(ith).step_by(num_threads) {
-                    if feat.is_monotonic() {
-                        // 特徴量が単調なケースは別途計算するのでスキップ
-                        continue;
-                    }
-                    let skeletons_stabilizers =
-                        remove_duplicates(skeletons.iter().copied(), feat, perm_store, 0);
-                    for (skeleton, stabilizer) in skeletons_stabilizers {
-                        let dehydrogenated = generate_dehydrogenated(skeleton.into(), &stabilizer);
-                        sender.send(dehydrogenated).unwrap();
+                while let Some(job) = next_job(jobs) {
+                    match job {
+                        Job::Monotonic(feat, skeletons) => {
+                            let skeletons_stabilizers = remove_duplicates(
+                                skeletons.iter().copied(),
+                                feat,
+                                perm_store,
+                                pre_alloc,
+                            )?;
+                            for (skeleton, stabilizer) in skeletons_stabilizers {
+                                send(skeleton, &stabilizer)?;
+                            }
+                        }
+                        Job::Chunk(ith) => {
+                            for (&feat, skeletons) in f2s.iter().skip(ith).step_by(num_chunks) {
+                                if feat.is_monotonic() {
+                                    // 特徴量が単調なケースは別途計算するのでスキップ
+                                    continue;
+                                }
+                                let skeletons_stabilizers = remove_duplicates(
+                                    skeletons.iter().copied(),
+                                    feat,
+                                    perm_store,
+                                    0,
+                                )?;
+                                for (skeleton, stabilizer) in skeletons_stabilizers {
+                                    send(skeleton, &stabilizer)?;
+                                }
+                            }
+                        }
                     }
                 }
-            });
+                Ok(())
+            }));
         }
         drop(sender); // 最初の1個が余るので手動で drop
 
-        for dehydrogenated in receiver {
-            hydrocarbons.extend(dehydrogenated);
-        }
-    });
+        // consume でエラーが起きた場合は receiver を drop してワーカーを止める
+        let consume_result = receiver.into_iter().try_for_each(&mut consume);
+        let workers_result = join_all(handles);
+        consume_result.and(workers_result)
+    })
+}
 
-    hydrocarbons
+enum Job<'a, const N: usize, S: Storage> {
+    Monotonic(Features<N>, &'a [AdjacencyBitMatrix<N, S>]),
+    Chunk(usize),
 }
 
-fn remove_duplicates<const N: usize>(
-    skeletons: impl Iterator<Item = AdjacencyBitMatrix<N>>,
+fn next_job<J>(jobs: &Mutex<impl Iterator<Item = J>>) -> Option<J> {
+    // 他のスレッドが panic していても残りのジョブは取り出せる
+    jobs.lock().unwrap_or_else(PoisonError::into_inner).next()
+}
+
+fn join_all(
+    handles: Vec<thread::ScopedJoinHandle<'_, Result<(), EnumerationError>>>,
+) -> Result<(), EnumerationError> {
+    let mut result = Ok(());
+    for handle in handles {
+        let joined = handle
+            .join()
+            .unwrap_or(Err(EnumerationError::WorkerPanicked));
+        if result.is_ok() {
+            result = joined;
+        }
+    }
+    result
+}
+
+type SkeletonStabilizer<const N: usize, S> = (AdjacencyBitMatrix<N, S>, Vec<Permutation<N>>);
+
+fn remove_duplicates<const N: usize, S: Storage>(
+    skeletons: impl Iterator<Item = AdjacencyBitMatrix<N, S>>,
     feat: Features<N>,
-    perm_store: &PermutationStore<N>,
+    perm_store: &PermutationStore<N, S>,
     orbit_pre_alloc: usize,
-) -> Vec<(AdjacencyBitMatrix<N>, Vec<Permutation<N>>)> {
+) -> Result<Vec<SkeletonStabilizer<N, S>>, EnumerationError> {
     let mut skeletons_stabilizers = Vec::new();
     let mut seen_orbits = FxHashSet::default();
-    let generators = perm_store.get(&feat.make_key()).unwrap();
+    let generators = perm_store
+        .get(&feat.make_key())
+        .ok_or(EnumerationError::MissingPermutations)?;
     for skeleton in skeletons {
         if seen_orbits.contains(&skeleton) {
             continue;
         }
-        let (orbit, stabilizer) = calc_orbit_stabilizer(skeleton, generators, orbit_pre_alloc);
+        let (orbit, stabilizer) = calc_orbit_stabilizer(skeleton, &generators, orbit_pre_alloc);
         seen_orbits.extend(orbit);
         skeletons_stabilizers.push((skeleton, stabilizer));
     }
-    skeletons_stabilizers
+    Ok(skeletons_stabilizers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            enumeration::{count_isomers, count_isomers_with_excluded, EnumerationConfig},
+            storage::{Carbon, SelectStorage, Storage16, Storage32, Storage64},
+            testing::count_edges,
+        },
+        *,
+    };
+
+    // 公開 API は炭素数から行の型を選ぶため, 17 炭素以上で使う型を小さな N で直接動かす
+    fn count_with_storage<const N: usize, S: Storage>() -> Vec<usize> {
+        let feat2skeletons =
+            create_feat2skeletons::<N, S>(N - 3, SkeletonConstraints::default(), None).unwrap();
+        let options = DehydrogenationOptions {
+            filters: &[],
+            num_hydrogens: None,
+            plausibility: PlausibilityRules::default(),
+            collapse_resonance: false,
+        };
+        count_feat2skeletons(
+            feat2skeletons,
+            &PermutationStore::new(),
+            1024,
+            None,
+            &options,
+            false,
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn wide_storages() {
+        let expected = count_with_storage::<8, Storage16>();
+        assert_eq!(count_with_storage::<8, Storage32>(), expected);
+        assert_eq!(count_with_storage::<8, Storage64>(), expected);
+    }
+
+    // 除かれた数を同じ探索で数えた結果が, 規則の有無による列挙の差に一致することを確かめる.
+    // 鎖状のフィルタを与えると, 炭化水素を生成してから振り分ける経路を通る
+    fn assert_excluded_counts<const N: usize>(acyclic: bool)
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let config = |no_cumulated_double_bonds| EnumerationConfig::<N> {
+            filters: if acyclic {
+                vec![Box::new(|h: &AdjacencyTwoBitsMatrix<N, StorageOf<N>>| {
+                    count_edges(h) == N - 1
+                })]
+            } else {
+                Vec::new()
+            },
+            plausibility: PlausibilityRules {
+                no_cumulated_double_bonds,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let all = count_isomers(config(false)).unwrap();
+        let kept = count_isomers(config(true)).unwrap();
+        let (counts, excluded) = count_isomers_with_excluded(config(true)).unwrap();
+        assert_eq!(counts, kept, "C = {N}, acyclic = {acyclic}");
+        let diff: Vec<_> = all.iter().zip(&kept).map(|(a, k)| a - k).collect();
+        assert_eq!(excluded, diff, "C = {N}, acyclic = {acyclic}");
+
+        // 規則が無効なら何も除かれない
+        let (counts, excluded) = count_isomers_with_excluded(config(false)).unwrap();
+        assert_eq!(counts, all, "C = {N}, acyclic = {acyclic}");
+        assert!(
+            excluded.iter().all(|&x| x == 0),
+            "C = {N}, acyclic = {acyclic}"
+        );
+    }
+
+    #[test]
+    fn excluded_counts() {
+        for acyclic in [false, true] {
+            assert_excluded_counts::<3>(acyclic);
+            assert_excluded_counts::<4>(acyclic);
+            assert_excluded_counts::<5>(acyclic);
+            assert_excluded_counts::<6>(acyclic);
+            assert_excluded_counts::<7>(acyclic);
+        }
+    }
 }
@@ -1,14 +1,19 @@
-use std::{collections::VecDeque, hash::Hash};
+use std::{borrow::Cow, collections::VecDeque, hash::Hash};
 
 use rustc_hash::{FxHashMap, FxHashSet};
 
+use super::{
+    hot_bit_iter::HotBitIter,
+    storage::{Bits, Storage, Storage16},
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Permutation<const N: usize> {
     raw: [usize; N],
 }
 
 impl<const N: usize> Permutation<N> {
-    const fn new(raw: [usize; N]) -> Self {
+    pub(super) const fn new(raw: [usize; N]) -> Self {
         Self { raw }
     }
 
@@ -43,44 +48,70 @@ impl<const N: usize> Permutation<N> {
     fn permute<P: Permutable<N>>(&self, permutable: &P) -> P {
         permutable.permute_by(&self.raw)
     }
+
+    /// 頂点 `i` の移り先
+    pub fn apply(&self, i: usize) -> usize {
+        self.raw[i]
+    }
 }
 
 impl<const N: usize> std::ops::Mul for Permutation<N> {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let mut raw = [0; N];
-        for i in 0..N {
-            raw[i] = self.raw[rhs.raw[i]];
-        }
-        Self::new(raw)
+        Self::new(rhs.raw.map(|i| self.raw[i]))
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct PermutationStore<const N: usize> {
-    memory: FxHashMap<u16, Vec<Permutation<N>>>,
+pub struct PermutationStore<const N: usize, S: Storage = Storage16> {
+    memory: FxHashMap<S::Row, Vec<Permutation<N>>>,
 }
 
-impl<const N: usize> PermutationStore<N> {
+impl<const N: usize, S: Storage> PermutationStore<N, S> {
+    // キーは 2^(N-1) 通りあるので, これを超える N では事前計算せず都度生成する
+    const MAX_PRECOMPUTED_N: usize = 16;
+
     pub fn new() -> Self {
         let mut memory = FxHashMap::default();
-        for bits in (0..(1 << (N - 1))).map(|x| x << 1) {
-            let key_array = Self::bits_to_key_array(bits);
-            let perm_vec = Self::generate_perms(key_array);
-            memory.insert(bits, perm_vec);
+        if N <= Self::MAX_PRECOMPUTED_N {
+            for bits in (0..(1 << (N - 1))).map(|x: u32| x << 1) {
+                let bits = Self::key_from_u32(bits);
+                let key_array = Self::bits_to_key_array(bits);
+                let perm_vec = Self::generate_perms(key_array);
+                memory.insert(bits, perm_vec);
+            }
         }
         Self { memory }
     }
 
-    pub fn get(&self, key: &u16) -> Option<&[Permutation<N>]> {
-        self.memory.get(key).map(|v| v.as_slice())
+    pub fn get(&self, key: &S::Row) -> Option<Cow<'_, [Permutation<N>]>> {
+        if let Some(perms) = self.memory.get(key) {
+            return Some(Cow::Borrowed(perms));
+        }
+        if N <= Self::MAX_PRECOMPUTED_N {
+            return None;
+        }
+        let out_of_range = S::Row::ONE | !S::Row::repunit(N);
+        if *key & out_of_range != S::Row::ZERO {
+            return None;
+        }
+        let key_array = Self::bits_to_key_array(*key);
+        Some(Cow::Owned(Self::generate_perms(key_array)))
+    }
+
+    fn key_from_u32(bits: u32) -> S::Row {
+        let mut key = S::Row::ZERO;
+        for i in HotBitIter::from(bits) {
+            key |= S::Row::ONE << i;
+        }
+        key
     }
 
-    fn bits_to_key_array(bits: u16) -> [u8; N] {
+    fn bits_to_key_array(bits: S::Row) -> [u8; N] {
         let mut key_array = [0; N];
         for (i, item) in key_array.iter_mut().enumerate() {
-            *item = ((bits >> i) & 1) as u8;
+            *item = ((bits >> i) & S::Row::ONE).low_u32() as u8;
         }
         key_array
     }
@@ -110,6 +141,17 @@ pub trait Permutable<const N: usize> {
     fn permute_by(&self, perm: &[usize; N]) -> Self;
 }
 
+/// 頂点ごとのラベルの並び
+impl<const N: usize, T: Copy> Permutable<N> for [T; N] {
+    fn permute_by(&self, perm: &[usize; N]) -> Self {
+        let mut permuted = *self;
+        for (i_old, &i_new) in perm.iter().enumerate() {
+            permuted[i_new] = self[i_old];
+        }
+        permuted
+    }
+}
+
 pub fn calc_orbit_stabilizer<const N: usize, P>(
     pable: P,
     generators: &[Permutation<N>],
@@ -168,3 +210,241 @@ where
     }
     orbit
 }
+
+/// ラベルの多重集合 `labels` を頂点に並べる方法のうち, 全ての頂点 `i` で `fits(i, label)` を満たすものを,
+/// `generators` の生成する群で移り合うものを除いて全て返す. `fits` は群の作用で不変でなければならない.
+/// 最も多いラベル以外を1つずつ, 途中までの並べ方の固定部分群の軌道ごとに1頂点だけ選んで置き,
+/// 最も多いラベルを残りの頂点に置く. 同値な並べ方を全ての並べ方から探さずに済む.
+/// 併せて返す置換は, 並べ方を保つ固定部分群の生成元
+pub fn assign_labels<const N: usize, T>(
+    labels: [T; N],
+    generators: &[Permutation<N>],
+    fits: impl Fn(usize, T) -> bool,
+) -> Vec<([T; N], Vec<Permutation<N>>)>
+where
+    T: Copy + Ord + Hash,
+{
+    let mut sorted = labels;
+    sorted.sort();
+    let mut counts: Vec<(T, usize)> = Vec::new();
+    for label in sorted {
+        match counts.last_mut() {
+            Some((last, count)) if *last == label => *count += 1,
+            _ => counts.push((label, 1)),
+        }
+    }
+    let Some(fill_index) = (0..counts.len()).max_by_key(|&i| counts[i].1) else {
+        return vec![(labels, generators.to_vec())];
+    };
+    let (fill, _) = counts.remove(fill_index);
+
+    let mut partials = vec![([None; N], generators.to_vec())];
+    for (label, count) in counts {
+        for _ in 0..count {
+            let mut seen_orbits = FxHashSet::default();
+            let mut extended_partials = Vec::new();
+            for (partial, stabilizer) in &partials {
+                let is_free = |v: usize| partial[v].is_none() && fits(v, label);
+                for v in orbit_representatives(stabilizer, is_free) {
+                    let mut extended = *partial;
+                    extended[v] = Some(label);
+                    if seen_orbits.contains(&extended) {
+                        continue;
+                    }
+                    let (orbit, stabilizer) = calc_orbit_stabilizer(extended, generators, 0);
+                    seen_orbits.extend(orbit);
+                    extended_partials.push((extended, stabilizer));
+                }
+            }
+            partials = extended_partials;
+        }
+    }
+    partials
+        .into_iter()
+        .filter(|(partial, _)| (0..N).all(|v| partial[v].is_some() || fits(v, fill)))
+        .map(|(partial, stabilizer)| (partial.map(|label| label.unwrap_or(fill)), stabilizer))
+        .collect()
+}
+
+// `generators` の生成する群の軌道のうち `candidate` を満たす頂点からなるものについて,
+// 最小の頂点を返す. `candidate` を満たす頂点の集合は群の作用で保たれなければならない
+fn orbit_representatives<const N: usize>(
+    generators: &[Permutation<N>],
+    candidate: impl Fn(usize) -> bool,
+) -> Vec<usize> {
+    let mut visited = [false; N];
+    let mut representatives = Vec::new();
+    for start in (0..N).filter(|&v| candidate(v)) {
+        if visited[start] {
+            continue;
+        }
+        representatives.push(start);
+        visited[start] = true;
+        let mut stack = vec![start];
+        while let Some(v) = stack.pop() {
+            for g in generators {
+                let w = g.apply(v);
+                if !visited[w] {
+                    visited[w] = true;
+                    stack.push(w);
+                }
+            }
+        }
+    }
+    representatives
+}
+
+/// 生成元で生成される群の位数.
+/// Schreier–Sims 法で固定部分群の列を作るので, 群の元を列挙せず頂点数の多項式時間で求まる
+pub fn group_order<const N: usize>(generators: &[Permutation<N>]) -> u128 {
+    let mut levels = Vec::new();
+    for &g in generators {
+        if let Some((residue, level)) = sift(&levels, g, 0) {
+            add_generator(&mut levels, level, residue);
+        }
+    }
+    levels
+        .iter()
+        .map(|level| level.transversal.iter().flatten().count() as u128)
+        .product()
+}
+
+// 固定部分群の列の1段. それより前の段の基点を全て固定する部分群の生成元と,
+// 基点の軌道の各点 x について基点を x に移す元を持つ
+struct StabilizerLevel<const N: usize> {
+    base: usize,
+    generators: Vec<Permutation<N>>,
+    transversal: [Option<Permutation<N>>; N],
+}
+
+// `g` を `levels[start..]` でふるう. 恒等置換にならなければ, 残った元と止まった段を返す
+fn sift<const N: usize>(
+    levels: &[StabilizerLevel<N>],
+    mut g: Permutation<N>,
+    start: usize,
+) -> Option<(Permutation<N>, usize)> {
+    for (i, level) in levels.iter().enumerate().skip(start) {
+        match level.transversal[g.apply(level.base)] {
+            Some(u) => g = u.inverse() * g,
+            None => return Some((g, i)),
+        }
+    }
+    (g != Permutation::IDENTITY).then_some((g, levels.len()))
+}
+
+// `levels[..level]` の基点を全て固定する `g` を `levels[..=level]` の生成元に加え,
+// 深い段から順に軌道を広げて Schreier 生成元を後ろの段に加える
+fn add_generator<const N: usize>(
+    levels: &mut Vec<StabilizerLevel<N>>,
+    level: usize,
+    g: Permutation<N>,
+) {
+    if level == levels.len() {
+        let base = (0..N).find(|&i| g.apply(i) != i).unwrap();
+        let mut transversal = [None; N];
+        transversal[base] = Some(Permutation::IDENTITY);
+        levels.push(StabilizerLevel {
+            base,
+            generators: Vec::new(),
+            transversal,
+        });
+    }
+    for current in &mut levels[..=level] {
+        current.generators.push(g);
+    }
+    for l in (0..=level).rev() {
+        close_level(levels, l);
+    }
+}
+
+fn close_level<const N: usize>(levels: &mut Vec<StabilizerLevel<N>>, level: usize) {
+    let current = &mut levels[level];
+    let mut queue: VecDeque<_> = (0..N)
+        .filter(|&x| current.transversal[x].is_some())
+        .collect();
+    while let Some(x) = queue.pop_front() {
+        let u = current.transversal[x].unwrap();
+        for &s in &current.generators {
+            let y = s.apply(x);
+            if current.transversal[y].is_none() {
+                current.transversal[y] = Some(s * u);
+                queue.push_back(y);
+            }
+        }
+    }
+
+    // 後ろの段に加える元はこの段の群に含まれるので, この段の軌道は変わらない
+    let base = current.base;
+    let generators = current.generators.clone();
+    let transversal = current.transversal;
+    for u in transversal.iter().flatten() {
+        for &s in &generators {
+            let v = transversal[s.apply(u.apply(base))].unwrap();
+            let schreier = v.inverse() * s * *u;
+            if let Some((residue, next)) = sift(levels, schreier, level + 1) {
+                add_generator(levels, next, residue);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 頂点 0..n の互換 (0 1) と巡回置換 (0 1 ... n-1) は対称群 S_n を生成する
+    fn symmetric_group_generators<const N: usize>(n: usize) -> [Permutation<N>; 2] {
+        let mut cycle = Permutation::INDEX_ARRAY;
+        cycle[..n].rotate_left(1);
+        [Permutation::new_cyclic(0, 1), Permutation::new(cycle)]
+    }
+
+    #[test]
+    fn group_order() {
+        assert_eq!(super::group_order::<6>(&[]), 1);
+        assert_eq!(super::group_order(&symmetric_group_generators::<6>(4)), 24);
+        // 20! 個の元は列挙できないが, 位数は求まる
+        let factorial = (1..=20).product::<u128>();
+        assert_eq!(
+            super::group_order(&symmetric_group_generators::<24>(20)),
+            factorial
+        );
+        // 2 つの三角形を入れ替える群 (S_3 × S_3) ⋊ C_2 の位数は 72
+        let swap = Permutation::new([3, 4, 5, 0, 1, 2]);
+        let rotate = Permutation::new([1, 2, 0, 3, 4, 5]);
+        let flip = Permutation::new([1, 0, 2, 3, 4, 5]);
+        assert_eq!(super::group_order(&[swap, rotate, flip]), 72);
+        assert_eq!(super::group_order(&[rotate, rotate * rotate]), 3);
+    }
+
+    #[test]
+    fn assign_labels() {
+        // 対称群のもとでは, ラベルの並べ方は多重集合ごとに1通り
+        let labels = [0, 0, 1, 1, 2, 0];
+        let symmetric = symmetric_group_generators::<6>(6);
+        let assigned = super::assign_labels(labels, &symmetric, |_, _| true);
+        assert_eq!(assigned.len(), 1);
+        // 固定部分群は S_3 × S_2
+        assert_eq!(super::group_order(&assigned[0].1), 12);
+        // 自明な群のもとでは多項係数 6! / (3! 2! 1!) 通り
+        assert_eq!(super::assign_labels(labels, &[], |_, _| true).len(), 60);
+        // 六角形の回転と鏡映のもとで, 2 つの 1 の置き方はオルト, メタ, パラの3通り
+        let rotate = Permutation::new([1, 2, 3, 4, 5, 0]);
+        let reflect = Permutation::new([0, 5, 4, 3, 2, 1]);
+        let hexagon = [rotate, reflect];
+        assert_eq!(
+            super::assign_labels([1, 1, 0, 0, 0, 0], &hexagon, |_, _| true).len(),
+            3
+        );
+        // 1, 1, 2 の置き方は Burnside の補題より (60 + 3 * 4) / 12 = 6 通り
+        assert_eq!(
+            super::assign_labels([1, 1, 2, 0, 0, 0], &hexagon, |_, _| true).len(),
+            6
+        );
+        // 頂点 0 に 1 を置けない場合は, 残りの 5 頂点から選ぶ
+        let fits = |v: usize, label: i32| v != 0 || label != 1;
+        let assigned = super::assign_labels([1, 0, 0, 0, 0, 0], &[], fits);
+        assert_eq!(assigned.len(), 5);
+        assert!(assigned.iter().all(|(labels, _)| labels[0] == 0));
+    }
+}
@@ -0,0 +1,381 @@
+use super::{
+    hot_bit_iter::HotBitIter,
+    matrix::AdjacencyTwoBitsMatrix,
+    storage::{Bits, Storage},
+};
+
+// トランス二重結合を収められる最小の環の大きさ
+pub const MIN_RING_SIZE_FOR_TRANS: usize = 8;
+// 三重結合を含められる最小の環の大きさ
+const MIN_RING_SIZE_FOR_TRIPLE: usize = 8;
+// アレンを含められる最小の環の大きさ
+const MIN_RING_SIZE_FOR_ALLENE: usize = 9;
+
+/// 脱水素化で不飽和結合を置く際の, 化学的に妥当かどうかの規則. 既定では全て無効
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlausibilityRules {
+    /// 8 員環未満の環に三重結合を置かない
+    pub no_triple_bonds_in_small_rings: bool,
+    /// 二重結合の周りの置換基が, 8 員環未満の環でトランス配置を強いられる場合を除く.
+    /// 二重結合の両端が橋頭位 (環に含まれる結合を3本以上持つ原子) の場合に適用する
+    pub no_trans_double_bonds_in_small_rings: bool,
+    /// Bredt 則: 二重結合の片端のみが橋頭位で, トランス配置を強いられる場合を除く
+    pub bredt: bool,
+    /// 9 員環未満の環にアレン (同じ原子から出る2本の二重結合) を置かない
+    pub no_allenes_in_small_rings: bool,
+    /// 累積二重結合 (二重結合を2本, または二重結合と三重結合を持つ原子) を置かない.
+    /// 環の歪みとは無関係なので `all` には含めない
+    pub no_cumulated_double_bonds: bool,
+}
+
+impl PlausibilityRules {
+    /// 小さな環の歪みに関する規則を全て有効にする
+    pub fn all() -> Self {
+        Self {
+            no_triple_bonds_in_small_rings: true,
+            no_trans_double_bonds_in_small_rings: true,
+            bredt: true,
+            no_allenes_in_small_rings: true,
+            no_cumulated_double_bonds: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 二重結合以上の結合を2本以上持つ原子があるか. `no_cumulated_double_bonds` で除かれる炭化水素に当たる
+pub fn has_cumulated_double_bonds<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+) -> bool {
+    (0..N).any(|i| hydrocarbon.count_multiple_bonds_of(i) >= 2)
+}
+
+/// 骨格ごとに前計算した, 結合次数の上限と同時に二重結合にできない結合の組
+pub struct BondLimits<const N: usize, S: Storage> {
+    max_orders: [[u32; N]; N],
+    // allene_partners[u][a] は, 結合 (u, a) と同時に二重結合以上にできない u の隣接原子 b の集合
+    allene_partners: [[S::Row; N]; N],
+    no_cumulated: bool,
+}
+
+impl<const N: usize, S: Storage> BondLimits<N, S> {
+    /// 規則が空の場合は `None` を返す
+    pub fn new(skeleton: &AdjacencyTwoBitsMatrix<N, S>, rules: &PlausibilityRules) -> Option<Self> {
+        if rules.is_empty() {
+            return None;
+        }
+
+        let graph = Graph::<N, S>::from(skeleton);
+        let mut max_orders = [[3; N]; N];
+        let mut allene_partners = [[S::Row::ZERO; N]; N];
+        for u in 0..N {
+            for v in HotBitIter::from(graph.rows[u]).filter(|&v| v > u) {
+                let mut max_order = 3;
+                if rules.no_triple_bonds_in_small_rings
+                    && graph
+                        .shortest_ring_through(u, v)
+                        .is_some_and(|size| size < MIN_RING_SIZE_FOR_TRIPLE)
+                {
+                    max_order = 2;
+                }
+                if let Some(num_bridgeheads) = graph.trans_conflict(u, v) {
+                    let is_forbidden = match num_bridgeheads {
+                        2 => rules.no_trans_double_bonds_in_small_rings,
+                        1 => rules.bredt,
+                        // 端ごとに相手以外の隣接原子は高々2つなので, シス配置にできないのは
+                        // 一方の端の隣接原子 a が, 他方の端 v の2つの隣接原子 b, b' の両方と
+                        // u, v を通らない経路で結ばれる場合に限る. このとき a-u-v-b と a-u-v-b' の
+                        // 環が v の3本の結合を全て含むので, v は橋頭位になる
+                        _ => unreachable!("a trans conflict needs a bridgehead"),
+                    };
+                    if is_forbidden {
+                        max_order = 1;
+                    }
+                }
+                max_orders[u][v] = max_order;
+                max_orders[v][u] = max_order;
+            }
+
+            if rules.no_allenes_in_small_rings {
+                for a in HotBitIter::from(graph.rows[u]) {
+                    for b in HotBitIter::from(graph.rows[u]).filter(|&b| b != a) {
+                        let excluded = S::Row::ONE << u;
+                        if graph
+                            .distance(a, b, excluded)
+                            .is_some_and(|d| d + 2 < MIN_RING_SIZE_FOR_ALLENE)
+                        {
+                            allene_partners[u][a] |= S::Row::ONE << b;
+                        }
+                    }
+                }
+            }
+        }
+
+        Some(Self {
+            max_orders,
+            allene_partners,
+            no_cumulated: rules.no_cumulated_double_bonds,
+        })
+    }
+
+    /// 結合 (irow, icol) の次数を1つ上げても規則に反しないか.
+    /// 規則はいずれも次数を上げるほど破れやすくなるので, 反した時点で枝刈りしてよい
+    pub fn allows_increment(
+        &self,
+        hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+        irow: usize,
+        icol: usize,
+    ) -> bool {
+        let order = hydrocarbon.element_at(irow, icol) + 1;
+        if order > self.max_orders[irow][icol] {
+            return false;
+        }
+        if order != 2 {
+            return true;
+        }
+        // 2 -> 3 の増加では二重結合以上の結合の数は変わらないので, 2 になる時だけ調べればよい
+        if self.no_cumulated
+            && (hydrocarbon.count_multiple_bonds_of(irow) > 0
+                || hydrocarbon.count_multiple_bonds_of(icol) > 0)
+        {
+            return false;
+        }
+        [(irow, icol), (icol, irow)].into_iter().all(|(u, a)| {
+            HotBitIter::from(self.allene_partners[u][a]).all(|b| hydrocarbon.element_at(u, b) < 2)
+        })
+    }
+}
+
+// 結合次数を無視した骨格のグラフ
+pub struct Graph<const N: usize, S: Storage> {
+    rows: [S::Row; N],
+}
+
+impl<const N: usize, S: Storage> From<&AdjacencyTwoBitsMatrix<N, S>> for Graph<N, S> {
+    fn from(skeleton: &AdjacencyTwoBitsMatrix<N, S>) -> Self {
+        let mut rows = [S::Row::ZERO; N];
+        for (irow, row) in rows.iter_mut().enumerate() {
+            for icol in 0..N {
+                if skeleton.element_at(irow, icol) != 0 {
+                    *row |= S::Row::ONE << icol;
+                }
+            }
+        }
+        Self { rows }
+    }
+}
+
+impl<const N: usize, S: Storage> Graph<N, S> {
+    // excluded に含まれる原子を通らない from から to への最短距離
+    fn distance(&self, from: usize, to: usize, excluded: S::Row) -> Option<usize> {
+        let target = S::Row::ONE << to;
+        let mut visited = (S::Row::ONE << from) | excluded;
+        let mut frontier = S::Row::ONE << from;
+        if frontier == target {
+            return Some(0);
+        }
+        for dist in 1..N {
+            let mut next = S::Row::ZERO;
+            for i in HotBitIter::from(frontier) {
+                next |= self.rows[i];
+            }
+            next &= !visited;
+            if next & target != S::Row::ZERO {
+                return Some(dist);
+            }
+            if next == S::Row::ZERO {
+                return None;
+            }
+            visited |= next;
+            frontier = next;
+        }
+        None
+    }
+
+    pub fn shortest_ring_through(&self, u: usize, v: usize) -> Option<usize> {
+        HotBitIter::from(self.rows[u])
+            .filter(|&a| a != v)
+            .filter_map(|a| self.distance(a, v, S::Row::ONE << u))
+            .map(|d| d + 2)
+            .min()
+    }
+
+    fn is_bridgehead(&self, u: usize) -> bool {
+        HotBitIter::from(self.rows[u])
+            .filter(|&v| self.shortest_ring_through(u, v).is_some())
+            .count()
+            >= 3
+    }
+
+    // 結合 (u, v) を二重結合にすると, u, v の他の隣接原子を小さな環で全てシス配置にできない場合,
+    // 両端のうち橋頭位である原子の数を返す
+    fn trans_conflict(&self, u: usize, v: usize) -> Option<usize> {
+        let others = |w: usize, x: usize| -> Vec<usize> {
+            HotBitIter::from(self.rows[w]).filter(|&y| y != x).collect()
+        };
+        let (us, vs) = (others(u, v), others(v, u));
+        // 3 つ以上の隣接原子を持つ原子は二重結合を持てない
+        if us.len() > 2 || vs.len() > 2 {
+            return None;
+        }
+
+        // a-u=v-b を含む環が小さいとき, a と b は二重結合の同じ側になければならない
+        let excluded = (S::Row::ONE << u) | (S::Row::ONE << v);
+        let mut cis_pairs = Vec::new();
+        for (i, &a) in us.iter().enumerate() {
+            for (j, &b) in vs.iter().enumerate() {
+                if self
+                    .distance(a, b, excluded)
+                    .is_some_and(|d| d + 3 < MIN_RING_SIZE_FOR_TRANS)
+                {
+                    cis_pairs.push((i, j));
+                }
+            }
+        }
+
+        // u 側の隣接原子を順に 0, 1 の側に置き, v 側の置き方を全て試す
+        let sides: &[[usize; 2]] = if vs.len() == 1 {
+            &[[0, 0], [1, 0]]
+        } else {
+            &[[0, 1], [1, 0]]
+        };
+        let is_feasible = sides
+            .iter()
+            .any(|side| cis_pairs.iter().all(|&(i, j)| i == side[j]));
+        if is_feasible {
+            return None;
+        }
+        Some(self.is_bridgehead(u) as usize + self.is_bridgehead(v) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            enumeration::{count_isomers, enumerate_skeletons, EnumerationConfig},
+            storage::{Carbon, SelectStorage, StorageOf},
+            testing::assert_same_counts_as_filter,
+        },
+        *,
+    };
+
+    // 規則のもとで二重結合にできる, 骨格の結合の数
+    fn count_double_bondable<const N: usize>(smiles: &str, rules: PlausibilityRules) -> usize
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let skeleton: AdjacencyTwoBitsMatrix<N, StorageOf<N>> = smiles.parse().unwrap();
+        let limits = BondLimits::new(&skeleton, &rules);
+        (0..N)
+            .flat_map(|u| (u + 1..N).map(move |v| (u, v)))
+            .filter(|&(u, v)| skeleton.element_at(u, v) != 0)
+            .filter(|&(u, v)| limits.as_ref().is_none_or(|l| l.max_orders[u][v] >= 2))
+            .count()
+    }
+
+    fn only(set: fn(&mut PlausibilityRules)) -> PlausibilityRules {
+        let mut rules = PlausibilityRules::default();
+        set(&mut rules);
+        rules
+    }
+
+    #[test]
+    fn bridgehead_double_bonds() {
+        let bredt = only(|r| r.bredt = true);
+        let no_trans = only(|r| r.no_trans_double_bonds_in_small_rings = true);
+        let both = only(|r| {
+            r.bredt = true;
+            r.no_trans_double_bonds_in_small_rings = true;
+        });
+        let none = PlausibilityRules::default();
+
+        // ノルボルナン: 8 本の結合のうち, 橋頭位から出る 6 本は二重結合にすると
+        // 片端のみが橋頭位のトランス配置になる. C2-C3 と C5-C6 のみが残る
+        let norbornane = "C1CC2CCC1C2";
+        assert_eq!(count_double_bondable::<7>(norbornane, none), 8);
+        assert_eq!(count_double_bondable::<7>(norbornane, bredt), 2);
+        assert_eq!(count_double_bondable::<7>(norbornane, no_trans), 8);
+
+        // ビシクロ[2.2.0]ヘキサン: 橋頭位どうしの結合は両側の四員環でシス配置にできるので残り,
+        // 橋頭位と橋頭位でない原子の間の 4 本が Bredt 則で除かれる
+        let bicyclohexane = "C1CC2CCC12";
+        assert_eq!(count_double_bondable::<6>(bicyclohexane, none), 7);
+        assert_eq!(count_double_bondable::<6>(bicyclohexane, bredt), 3);
+        assert_eq!(count_double_bondable::<6>(bicyclohexane, no_trans), 7);
+
+        // 四員環 a-u-v-b' の対角を b と a' で橋架けした三環式骨格. 橋頭位 a, u, v, b' の間の
+        // 4 本の結合は両端が橋頭位, b と a' から出る 4 本は片端のみが橋頭位で,
+        // いずれも二重結合にすると置換基をシス配置に収められない
+        let tricyclic = "C12C3C(C1)C2C3";
+        assert_eq!(count_double_bondable::<6>(tricyclic, none), 8);
+        assert_eq!(count_double_bondable::<6>(tricyclic, bredt), 4);
+        assert_eq!(count_double_bondable::<6>(tricyclic, no_trans), 4);
+        assert_eq!(count_double_bondable::<6>(tricyclic, both), 0);
+    }
+
+    // BondLimits::new で示した不変条件を, 小さな骨格で全て確かめる
+    fn assert_trans_conflicts_need_bridgeheads<const N: usize>()
+    where
+        Carbon<N>: SelectStorage,
+    {
+        for skeleton in enumerate_skeletons::<N>(EnumerationConfig::default()).unwrap() {
+            let graph = Graph::from(&AdjacencyTwoBitsMatrix::from(skeleton));
+            for u in 0..N {
+                for v in (u + 1..N).filter(|&v| skeleton.is_adjacent(u, v)) {
+                    if let Some(num_bridgeheads) = graph.trans_conflict(u, v) {
+                        assert!(
+                            matches!(num_bridgeheads, 1 | 2),
+                            "{}: ({u}, {v})",
+                            skeleton.to_graph6()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn trans_conflicts_need_bridgeheads() {
+        assert_trans_conflicts_need_bridgeheads::<4>();
+        assert_trans_conflicts_need_bridgeheads::<5>();
+        assert_trans_conflicts_need_bridgeheads::<6>();
+        assert_trans_conflicts_need_bridgeheads::<7>();
+        assert_trans_conflicts_need_bridgeheads::<8>();
+    }
+
+    // 累積二重結合の禁止による枝刈りが, 全列挙を絞り込んだ結果と一致することを確かめる
+    fn assert_non_cumulated_counts<const N: usize>() -> Vec<usize>
+    where
+        Carbon<N>: SelectStorage,
+    {
+        assert_same_counts_as_filter::<N>(
+            |h| !(0..N).any(|u| (0..N).filter(|&v| h.element_at(u, v) >= 2).count() >= 2),
+            |config| config.plausibility.no_cumulated_double_bonds = true,
+            "no_cumulated_double_bonds",
+        )
+    }
+
+    #[test]
+    fn non_cumulated() {
+        assert_non_cumulated_counts::<3>();
+        assert_non_cumulated_counts::<5>();
+        assert_non_cumulated_counts::<6>();
+        assert_non_cumulated_counts::<7>();
+
+        // C3H4 はプロピンとシクロプロペンのみ残り, プロパジエンが除かれる
+        let config = EnumerationConfig {
+            num_hydrogens: Some(4),
+            plausibility: only(|r| r.no_cumulated_double_bonds = true),
+            ..Default::default()
+        };
+        assert_eq!(count_isomers::<3>(config).unwrap()[4], 2);
+
+        // C4H6 の 9 つから 1,2-ブタジエンが除かれ, 1,3-ブタジエン, ブチン 2 種, シクロブテン,
+        // メチルシクロプロペン 2 種, メチレンシクロプロパン, ビシクロブタンが残る
+        let counts = assert_non_cumulated_counts::<4>();
+        assert_eq!(counts[6], 8);
+    }
+}
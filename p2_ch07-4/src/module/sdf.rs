@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+
+use super::{
+    layout::{hydrogen_directions, layout_2d},
+    matrix::AdjacencyTwoBitsMatrix,
+    smiles::to_smiles,
+    storage::Storage,
+};
+
+// 2次元座標での C-H 結合の長さ (Å)
+const HYDROGEN_BOND_LENGTH: f64 = 1.0;
+
+/// 炭化水素を MDL Molfile (V2000) として書く. 1行目の名前には正準 SMILES を使う.
+/// `explicit_hydrogens` が偽なら水素は書かず, 読み込む側が原子価から補う
+pub fn write_molfile<const N: usize, S: Storage, W: Write>(
+    writer: &mut W,
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+    explicit_hydrogens: bool,
+) -> io::Result<()> {
+    let positions = layout_2d(hydrocarbon);
+    let mut atoms: Vec<(&str, [f64; 2])> = positions.iter().map(|&p| ("C", p)).collect();
+    let mut bonds: Vec<(usize, usize, u32)> = Vec::new();
+    for i in 0..N {
+        for j in (i + 1)..N {
+            match hydrocarbon.element_at(i, j) {
+                0 => (),
+                order => bonds.push((i, j, order)),
+            }
+        }
+    }
+    if explicit_hydrogens {
+        for i in 0..N {
+            for angle in hydrogen_directions(hydrocarbon, &positions, i) {
+                let position = [
+                    positions[i][0] + HYDROGEN_BOND_LENGTH * angle.cos(),
+                    positions[i][1] + HYDROGEN_BOND_LENGTH * angle.sin(),
+                ];
+                bonds.push((i, atoms.len(), 1));
+                atoms.push(("H", position));
+            }
+        }
+    }
+
+    // ヘッダ: 名前, プログラム名と次元, コメント
+    writeln!(writer, "{}", to_smiles(hydrocarbon))?;
+    writeln!(writer, "  {:<8}{:10}2D", "hcrta", "")?;
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "{:>3}{:>3}  0  0  0  0  0  0  0  0999 V2000",
+        atoms.len(),
+        bonds.len()
+    )?;
+    for (symbol, position) in &atoms {
+        // 丸めた結果が "-0.0000" とならないよう, 負のゼロを正のゼロにする
+        let [x, y] = position.map(|v| (v * 1e4).round() / 1e4 + 0.0);
+        writeln!(
+            writer,
+            "{x:>10.4}{y:>10.4}{:>10.4} {symbol:<3} 0  0  0  0  0  0  0  0  0  0  0  0",
+            0.0
+        )?;
+    }
+    // Molfile の原子番号は 1 から始まる
+    for (i, j, order) in &bonds {
+        writeln!(writer, "{:>3}{:>3}{order:>3}  0", i + 1, j + 1)?;
+    }
+    writeln!(writer, "M  END")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            enumeration::{enumerate, EnumerationConfig},
+            sink::{IsomerSink, SdfSink},
+            testing::count_edges,
+        },
+        *,
+    };
+
+    #[test]
+    fn sdf() {
+        let hydrocarbons = enumerate::<5>(EnumerationConfig::default()).unwrap();
+        for explicit_hydrogens in [false, true] {
+            let mut sink = SdfSink::new(Vec::new(), explicit_hydrogens);
+            for &hydrocarbon in &hydrocarbons {
+                sink.push(hydrocarbon).unwrap();
+            }
+            let sdf = String::from_utf8(sink.into_inner()).unwrap();
+            let records: Vec<_> = sdf.split_terminator("$$$$\n").collect();
+            assert_eq!(records.len(), hydrocarbons.len());
+
+            for (record, hydrocarbon) in records.iter().zip(&hydrocarbons) {
+                let lines: Vec<_> = record.lines().collect();
+                assert_eq!(lines[0], to_smiles(hydrocarbon));
+                let num_atoms: usize = lines[3][0..3].trim().parse().unwrap();
+                let num_bonds: usize = lines[3][3..6].trim().parse().unwrap();
+                let num_h = if explicit_hydrogens {
+                    hydrocarbon.count_hydrogen()
+                } else {
+                    0
+                };
+                assert_eq!(num_atoms, 5 + num_h);
+                assert_eq!(num_bonds, count_edges(hydrocarbon) + num_h);
+                assert_eq!(lines.len(), 4 + num_atoms + num_bonds + 1);
+                assert_eq!(lines[lines.len() - 1], "M  END");
+
+                let positions: Vec<[f64; 2]> = lines[4..4 + num_atoms]
+                    .iter()
+                    .map(|line| [&line[0..10], &line[10..20]].map(|x| x.trim().parse().unwrap()))
+                    .collect();
+                let distance = |i: usize, j: usize| {
+                    (positions[i][0] - positions[j][0]).hypot(positions[i][1] - positions[j][1])
+                };
+                // 結合した原子は適度な距離にあり, 原子どうしは重ならない
+                for line in &lines[4 + num_atoms..4 + num_atoms + num_bonds] {
+                    let i: usize = line[0..3].trim().parse().unwrap();
+                    let j: usize = line[3..6].trim().parse().unwrap();
+                    let order: u32 = line[6..9].trim().parse().unwrap();
+                    if j <= 5 {
+                        assert_eq!(order, hydrocarbon.element_at(i - 1, j - 1));
+                        assert!(
+                            (0.9..2.1).contains(&distance(i - 1, j - 1)),
+                            "{hydrocarbon}"
+                        );
+                    }
+                }
+                for i in 0..5 {
+                    for j in (i + 1)..5 {
+                        assert!(distance(i, j) > 0.5, "{hydrocarbon}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::io::Write;
+
+use super::{
+    error::EnumerationError, matrix::AdjacencyTwoBitsMatrix, sdf::write_molfile, smiles::to_smiles,
+    storage::Storage,
+};
+
+/// 列挙された炭化水素を1つずつ受け取る
+///
+/// ワーカースレッドが生成した炭化水素は, 逐次 `push` に渡される.
+pub trait IsomerSink<const N: usize, S: Storage> {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError>;
+}
+
+impl<const N: usize, S: Storage> IsomerSink<N, S> for Vec<AdjacencyTwoBitsMatrix<N, S>> {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        Vec::push(self, hydrocarbon);
+        Ok(())
+    }
+}
+
+impl<const N: usize, S: Storage, T: IsomerSink<N, S> + ?Sized> IsomerSink<N, S> for &mut T {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        (**self).push(hydrocarbon)
+    }
+}
+
+/// 水素数ごとに炭化水素の個数を数える
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountingSink {
+    counts: Vec<usize>,
+}
+
+impl CountingSink {
+    pub fn new() -> Self {
+        Self { counts: Vec::new() }
+    }
+
+    /// 水素数が `num_h` の炭化水素の個数
+    pub fn count(&self, num_h: usize) -> usize {
+        self.counts.get(num_h).copied().unwrap_or(0)
+    }
+
+    /// 全ての炭化水素の個数
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+impl Default for CountingSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, S: Storage> IsomerSink<N, S> for CountingSink {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        let num_h = hydrocarbon.count_hydrogen();
+        if self.counts.len() <= num_h {
+            self.counts.resize(num_h + 1, 0);
+        }
+        self.counts[num_h] += 1;
+        Ok(())
+    }
+}
+
+/// 炭化水素の隣接行列を空行区切りで書き出す
+#[derive(Debug)]
+pub struct WriterSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> WriterSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<const N: usize, S: Storage, W: Write> IsomerSink<N, S> for WriterSink<W> {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        writeln!(self.writer, "{hydrocarbon}\n").map_err(EnumerationError::from)
+    }
+}
+
+/// 炭化水素の正準 SMILES を1行ずつ書き出す
+#[derive(Debug)]
+pub struct SmilesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> SmilesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<const N: usize, S: Storage, W: Write> IsomerSink<N, S> for SmilesSink<W> {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        writeln!(self.writer, "{}", to_smiles(&hydrocarbon)).map_err(EnumerationError::from)
+    }
+}
+
+/// 炭化水素を SDF のレコードとして1つずつ書き出す
+#[derive(Debug)]
+pub struct SdfSink<W: Write> {
+    writer: W,
+    explicit_hydrogens: bool,
+}
+
+impl<W: Write> SdfSink<W> {
+    /// `explicit_hydrogens` が真なら水素も原子として書く
+    pub fn new(writer: W, explicit_hydrogens: bool) -> Self {
+        Self {
+            writer,
+            explicit_hydrogens,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<const N: usize, S: Storage, W: Write> IsomerSink<N, S> for SdfSink<W> {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        write_molfile(&mut self.writer, &hydrocarbon, self.explicit_hydrogens)?;
+        writeln!(self.writer, "$$$$").map_err(EnumerationError::from)
+    }
+}
+
+/// 結合次数を辺の多重度とした sparse6 形式で, 炭化水素を1行ずつ書き出す
+#[derive(Debug)]
+pub struct Sparse6Sink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Sparse6Sink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<const N: usize, S: Storage, W: Write> IsomerSink<N, S> for Sparse6Sink<W> {
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        writeln!(self.writer, "{}", hydrocarbon.to_sparse6()).map_err(EnumerationError::from)
+    }
+}
+
+/// 条件を満たす炭化水素のみを内側の sink に渡す
+#[derive(Debug)]
+pub struct FilterSink<K, F> {
+    inner: K,
+    predicate: F,
+}
+
+impl<K, F> FilterSink<K, F> {
+    pub fn new(inner: K, predicate: F) -> Self {
+        Self { inner, predicate }
+    }
+
+    pub fn into_inner(self) -> K {
+        self.inner
+    }
+}
+
+impl<const N: usize, S, K, F> IsomerSink<N, S> for FilterSink<K, F>
+where
+    S: Storage,
+    K: IsomerSink<N, S>,
+    F: FnMut(&AdjacencyTwoBitsMatrix<N, S>) -> bool,
+{
+    fn push(&mut self, hydrocarbon: AdjacencyTwoBitsMatrix<N, S>) -> Result<(), EnumerationError> {
+        if (self.predicate)(&hydrocarbon) {
+            self.inner.push(hydrocarbon)?;
+        }
+        Ok(())
+    }
+}
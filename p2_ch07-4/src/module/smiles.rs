@@ -0,0 +1,454 @@
+use std::{fmt::Write, str::FromStr};
+
+use super::{
+    canon::canonical_order, error::EnumerationError, matrix::AdjacencyTwoBitsMatrix,
+    storage::Storage,
+};
+
+/// 炭化水素の正準 SMILES. 同型な炭化水素からは同じ文字列が得られる.
+/// 水素は省略し, 二重結合と三重結合は `=` と `#` で, 環の結合は環閉包の番号で書く.
+/// 芳香環も Kekulé 構造のまま書く
+pub fn to_smiles<const N: usize, S: Storage>(hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>) -> String {
+    let order = canonical_order(hydrocarbon);
+    let mut rank = [0; N];
+    for (r, &u) in order.iter().enumerate() {
+        rank[u] = r;
+    }
+    let neighbors: Vec<Vec<usize>> = (0..N)
+        .map(|u| {
+            let mut neighbors: Vec<_> = (0..N)
+                .filter(|&v| hydrocarbon.element_at(u, v) != 0)
+                .collect();
+            neighbors.sort_by_key(|&v| rank[v]);
+            neighbors
+        })
+        .collect();
+
+    let mut tree = SpanningTree {
+        neighbors: &neighbors,
+        visited: [false; N],
+        finished: [false; N],
+        children: vec![Vec::new(); N],
+        openings: vec![Vec::new(); N],
+        closings: vec![Vec::new(); N],
+    };
+    tree.visit(order[0], None);
+
+    let mut writer = SmilesWriter {
+        hydrocarbon,
+        tree: &tree,
+        smiles: String::new(),
+        digits: Vec::new(),
+    };
+    writer.write(order[0], None);
+    writer.smiles
+}
+
+// 深さ優先探索の全域木. 木に含まれない結合は, 先に訪れた原子で開き後に訪れた原子で閉じる環閉包になる
+struct SpanningTree<'a, const N: usize> {
+    neighbors: &'a [Vec<usize>],
+    visited: [bool; N],
+    finished: [bool; N],
+    children: Vec<Vec<usize>>,
+    openings: Vec<Vec<usize>>,
+    closings: Vec<Vec<usize>>,
+}
+
+impl<const N: usize> SpanningTree<'_, N> {
+    fn visit(&mut self, u: usize, parent: Option<usize>) {
+        self.visited[u] = true;
+        for &v in &self.neighbors[u] {
+            if Some(v) == parent || self.finished[v] {
+                continue;
+            }
+            if self.visited[v] {
+                // 探索中の祖先への結合
+                self.openings[v].push(u);
+                self.closings[u].push(v);
+            } else {
+                self.children[u].push(v);
+                self.visit(v, Some(u));
+            }
+        }
+        self.finished[u] = true;
+    }
+}
+
+struct SmilesWriter<'a, const N: usize, S: Storage> {
+    hydrocarbon: &'a AdjacencyTwoBitsMatrix<N, S>,
+    tree: &'a SpanningTree<'a, N>,
+    smiles: String,
+    // 使用中の環閉包の番号と, その結合の両端
+    digits: Vec<Option<(usize, usize)>>,
+}
+
+impl<const N: usize, S: Storage> SmilesWriter<'_, N, S> {
+    fn write(&mut self, u: usize, parent: Option<usize>) {
+        if let Some(p) = parent {
+            self.write_bond(p, u);
+        }
+        self.smiles.push('C');
+        for &v in &self.tree.openings[u] {
+            // 空いている最小の番号を使う
+            let digit = match self.digits.iter().position(Option::is_none) {
+                Some(digit) => digit,
+                None => {
+                    self.digits.push(None);
+                    self.digits.len() - 1
+                }
+            };
+            self.digits[digit] = Some((u, v));
+            self.write_bond(u, v);
+            self.write_digit(digit);
+        }
+        for &v in &self.tree.closings[u] {
+            let digit = self
+                .digits
+                .iter()
+                .position(|&d| d == Some((v, u)))
+                .expect("ring closures are opened before they are closed");
+            self.digits[digit] = None;
+            self.write_digit(digit);
+        }
+        let children = &self.tree.children[u];
+        for (i, &v) in children.iter().enumerate() {
+            if i + 1 < children.len() {
+                self.smiles.push('(');
+                self.write(v, Some(u));
+                self.smiles.push(')');
+            } else {
+                self.write(v, Some(u));
+            }
+        }
+    }
+
+    fn write_bond(&mut self, u: usize, v: usize) {
+        match self.hydrocarbon.element_at(u, v) {
+            2 => self.smiles.push('='),
+            3 => self.smiles.push('#'),
+            _ => (),
+        }
+    }
+
+    // 環閉包の番号は 1 から振り, 10 以上は `%` を付けて2桁で書く
+    fn write_digit(&mut self, digit: usize) {
+        match digit + 1 {
+            n @ 1..=9 => write!(self.smiles, "{n}"),
+            n => write!(self.smiles, "%{n}"),
+        }
+        .expect("writing to a String never fails");
+    }
+}
+
+impl<const N: usize, S: Storage> FromStr for AdjacencyTwoBitsMatrix<N, S> {
+    type Err = EnumerationError;
+
+    /// `CC(C)C` や `C1=CC=CC=C1` のような炭化水素の SMILES を読む.
+    /// 原子は角括弧なしの `C` のみで, 水素は暗黙に補う.
+    /// 分岐, 環閉包 (`%nn` を含む), 結合 `-`, `=`, `#` に対応し, 炭素数は `N` に一致する必要がある
+    fn from_str(smiles: &str) -> Result<Self, Self::Err> {
+        let parsed = SmilesParser::new(smiles).parse()?;
+        if parsed.atoms.len() != N {
+            return Err(EnumerationError::CarbonCountMismatch {
+                smiles: parsed.atoms.len(),
+                vertices: N,
+            });
+        }
+        let mut hydrocarbon = Self::empty();
+        for &(u, v, order, position) in &parsed.bonds {
+            // 同じ原子の組を2度結ぶことはできない
+            if u == v || hydrocarbon.element_at(u, v) != 0 {
+                return Err(EnumerationError::InvalidSmiles {
+                    smiles: smiles.to_string(),
+                    position,
+                });
+            }
+            for _ in 0..order {
+                hydrocarbon.increment_at(u, v);
+            }
+        }
+        if let Some(u) = (0..N).find(|&u| hydrocarbon.degree_of(u) > 4) {
+            return Err(EnumerationError::ValenceExceeded {
+                smiles: smiles.to_string(),
+                position: parsed.atoms[u],
+            });
+        }
+        Ok(hydrocarbon)
+    }
+}
+
+// 読み取った原子の位置と, 結合 (両端の原子, 次数, 位置) の一覧
+struct ParsedSmiles {
+    atoms: Vec<usize>,
+    bonds: Vec<(usize, usize, u32, usize)>,
+}
+
+struct SmilesParser<'a> {
+    smiles: &'a str,
+    position: usize,
+    atoms: Vec<usize>,
+    bonds: Vec<(usize, usize, u32, usize)>,
+    // 直前の原子. 次の原子はこれと結合する
+    prev: Option<usize>,
+    // 直前の原子が読み終わったところか. 環閉包の番号は原子の直後にのみ書ける
+    after_atom: bool,
+    // 次の結合の次数と位置
+    bond: Option<(u32, usize)>,
+    // 開いている分岐の起点の原子と, 分岐を開いた時点の原子数
+    branches: Vec<(usize, usize)>,
+    // 番号ごとに開いている環閉包の原子, 次数, 位置
+    rings: [Option<(usize, Option<u32>, usize)>; 100],
+}
+
+impl<'a> SmilesParser<'a> {
+    fn new(smiles: &'a str) -> Self {
+        Self {
+            smiles,
+            position: 0,
+            atoms: Vec::new(),
+            bonds: Vec::new(),
+            prev: None,
+            after_atom: false,
+            bond: None,
+            branches: Vec::new(),
+            rings: [None; 100],
+        }
+    }
+
+    fn parse(mut self) -> Result<ParsedSmiles, EnumerationError> {
+        let bytes = self.smiles.as_bytes();
+        while self.position < bytes.len() {
+            let c = bytes[self.position];
+            match c {
+                b'C' if bytes.get(self.position + 1) == Some(&b'l') => {
+                    return Err(self.unsupported());
+                }
+                b'C' => self.read_atom(),
+                b'-' | b'=' | b'#' => {
+                    if self.prev.is_none() || self.bond.is_some() {
+                        return Err(self.invalid());
+                    }
+                    let order = match c {
+                        b'-' => 1,
+                        b'=' => 2,
+                        _ => 3,
+                    };
+                    self.bond = Some((order, self.position));
+                    self.position += 1;
+                }
+                b'0'..=b'9' | b'%' => self.read_ring_closure()?,
+                b'(' => {
+                    let Some(prev) = self.prev else {
+                        return Err(self.invalid());
+                    };
+                    if self.bond.is_some() {
+                        return Err(self.invalid());
+                    }
+                    self.branches.push((prev, self.atoms.len()));
+                    self.after_atom = false;
+                    self.position += 1;
+                }
+                b')' => {
+                    let Some((origin, num_atoms)) = self.branches.pop() else {
+                        return Err(self.invalid());
+                    };
+                    // 空の分岐や結合で終わる分岐は誤り
+                    if self.atoms.len() == num_atoms || self.bond.is_some() {
+                        return Err(self.invalid());
+                    }
+                    self.prev = Some(origin);
+                    self.after_atom = false;
+                    self.position += 1;
+                }
+                b'[' | b'.' | b':' | b'$' | b'/' | b'\\' | b'@' | b'*' => {
+                    return Err(self.unsupported());
+                }
+                c if c.is_ascii_alphabetic() => return Err(self.unsupported()),
+                _ => return Err(self.invalid()),
+            }
+        }
+
+        if self.atoms.is_empty() || self.bond.is_some() || !self.branches.is_empty() {
+            return Err(self.invalid());
+        }
+        if let Some(&(_, _, position)) = self.rings.iter().flatten().next() {
+            self.position = position;
+            return Err(self.invalid());
+        }
+        Ok(ParsedSmiles {
+            atoms: self.atoms,
+            bonds: self.bonds,
+        })
+    }
+
+    fn read_atom(&mut self) {
+        let atom = self.atoms.len();
+        self.atoms.push(self.position);
+        if let Some(prev) = self.prev {
+            let (order, position) = self.bond.take().unwrap_or((1, self.position));
+            self.bonds.push((prev, atom, order, position));
+        }
+        self.prev = Some(atom);
+        self.after_atom = true;
+        self.position += 1;
+    }
+
+    // 1桁の数字, または `%` に続く2桁の数字を環閉包の番号として読む
+    fn read_ring_closure(&mut self) -> Result<(), EnumerationError> {
+        let (Some(atom), true) = (self.prev, self.after_atom) else {
+            return Err(self.invalid());
+        };
+        let start = self.position;
+        let (digits, len) = match self.smiles.as_bytes()[start] {
+            b'%' => (self.smiles.get(start + 1..start + 3), 3),
+            _ => (self.smiles.get(start..start + 1), 1),
+        };
+        let Some(number) = digits
+            .filter(|digits| digits.bytes().all(|c| c.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<usize>().ok())
+        else {
+            return Err(self.invalid());
+        };
+        let bond = self.bond.take();
+        match self.rings[number].take() {
+            None => self.rings[number] = Some((atom, bond.map(|(order, _)| order), start)),
+            Some((opened, order, _)) => {
+                // 両端で結合の次数を書く場合は一致する必要がある
+                let order = match (order, bond) {
+                    (Some(a), Some((b, _))) if a != b => return Err(self.invalid()),
+                    (Some(a), _) => a,
+                    (None, Some((b, _))) => b,
+                    (None, None) => 1,
+                };
+                self.bonds.push((opened, atom, order, start));
+            }
+        }
+        self.position += len;
+        Ok(())
+    }
+
+    fn invalid(&self) -> EnumerationError {
+        EnumerationError::InvalidSmiles {
+            smiles: self.smiles.to_string(),
+            position: self.position,
+        }
+    }
+
+    fn unsupported(&self) -> EnumerationError {
+        EnumerationError::UnsupportedSmiles {
+            smiles: self.smiles.to_string(),
+            position: self.position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            enumeration::{enumerate, EnumerationConfig},
+            storage::{Carbon, SelectStorage, StorageOf},
+        },
+        *,
+    };
+
+    fn assert_distinct_smiles<const N: usize>()
+    where
+        Carbon<N>: SelectStorage,
+    {
+        let hydrocarbons = enumerate::<N>(EnumerationConfig::default()).unwrap();
+        let smiles: std::collections::HashSet<_> = hydrocarbons.iter().map(to_smiles).collect();
+        assert_eq!(smiles.len(), hydrocarbons.len(), "C = {N}");
+    }
+
+    #[test]
+    fn smiles() {
+        let smiles = |config: EnumerationConfig<4>| -> Vec<_> {
+            let mut smiles: Vec<_> = enumerate::<4>(config)
+                .unwrap()
+                .iter()
+                .map(to_smiles)
+                .collect();
+            smiles.sort();
+            smiles
+        };
+        let config = EnumerationConfig {
+            num_hydrogens: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(smiles(config), ["CC(C)C", "CCCC"]);
+        let config = EnumerationConfig {
+            num_hydrogens: Some(4),
+            exact_rings: Some(1),
+            max_ring_size: Some(4),
+            min_ring_size: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(smiles(config), ["C1C=C=C1", "C1CC#C1", "C=1C=CC1"]);
+
+        // 異性体ごとに異なる SMILES が得られる
+        assert_distinct_smiles::<2>();
+        assert_distinct_smiles::<3>();
+        assert_distinct_smiles::<4>();
+        assert_distinct_smiles::<5>();
+        assert_distinct_smiles::<6>();
+    }
+
+    #[test]
+    fn smiles_parsing() {
+        fn canonical<const N: usize>(smiles: &str) -> String {
+            to_smiles(&smiles.parse::<AdjacencyTwoBitsMatrix<N>>().unwrap())
+        }
+        assert_eq!(canonical::<4>("C(C)CC"), canonical::<4>("CCCC"));
+        assert_eq!(canonical::<5>("C(C)(C)(C)C"), canonical::<5>("CC(C)(C)C"));
+        assert_eq!(
+            canonical::<6>("C1=CC=CC=C1"),
+            canonical::<6>("C=1C=CC=CC=1")
+        );
+        assert_eq!(canonical::<6>("C%10CCCCC%10"), "C1CCCCC1");
+        assert_eq!(canonical::<4>("C#CC=C"), canonical::<4>("C=CC#C"));
+
+        // 列挙された異性体の SMILES は読み戻すと同じ SMILES になる
+        for hydrocarbon in enumerate::<5>(EnumerationConfig::default()).unwrap() {
+            let smiles = to_smiles(&hydrocarbon);
+            let parsed: AdjacencyTwoBitsMatrix<5, StorageOf<5>> = smiles.parse().unwrap();
+            assert_eq!(to_smiles(&parsed), smiles);
+            assert_eq!(parsed.count_hydrogen(), hydrocarbon.count_hydrogen());
+        }
+
+        let parse = |smiles: &str| smiles.parse::<AdjacencyTwoBitsMatrix<4>>().unwrap_err();
+        let invalid = |smiles: &str, position| EnumerationError::InvalidSmiles {
+            smiles: smiles.to_string(),
+            position,
+        };
+        let unsupported = |smiles: &str, position| EnumerationError::UnsupportedSmiles {
+            smiles: smiles.to_string(),
+            position,
+        };
+        assert_eq!(parse(""), invalid("", 0));
+        assert_eq!(parse("CCC(C"), invalid("CCC(C", 5));
+        assert_eq!(parse("CC()CC"), invalid("CC()CC", 3));
+        assert_eq!(parse("C1CCC"), invalid("C1CCC", 1));
+        assert_eq!(parse("C1CCC=2"), invalid("C1CCC=2", 1));
+        assert_eq!(parse("CC1C1C"), invalid("CC1C1C", 4));
+        assert_eq!(parse("C=1CCC#1"), invalid("C=1CCC#1", 7));
+        assert_eq!(parse("CCCO"), unsupported("CCCO", 3));
+        assert_eq!(parse("CCCCl"), unsupported("CCCCl", 3));
+        assert_eq!(parse("c1ccc1"), unsupported("c1ccc1", 0));
+        assert_eq!(parse("C[CH2]CC"), unsupported("C[CH2]CC", 1));
+        assert_eq!(
+            parse("C=C(=C)=C"),
+            EnumerationError::ValenceExceeded {
+                smiles: "C=C(=C)=C".to_string(),
+                position: 2,
+            }
+        );
+        assert_eq!(
+            parse("CCC"),
+            EnumerationError::CarbonCountMismatch {
+                smiles: 3,
+                vertices: 4,
+            }
+        );
+    }
+}
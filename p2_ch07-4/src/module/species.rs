@@ -0,0 +1,105 @@
+use std::fmt;
+
+use super::{
+    matrix::AdjacencyTwoBitsMatrix,
+    permutation::{assign_labels, Permutation},
+    storage::Storage,
+};
+
+// 1つの炭素が持てる開いた原子価の上限. 少なくとも1本は他の炭素と結合する
+const MAX_OPEN_VALENCE: u32 = 3;
+
+/// ラジカルやイオンのような, 炭素の原子価が開いた化学種.
+/// 開いた原子価はラジカル中心の不対電子, カルボカチオンの空軌道, カルボアニオンの非共有電子対のいずれかで,
+/// 結合の数え上げの上では区別しない. 開いた原子価は1種類のラベルとしてのみ扱うため, 同じ骨格の
+/// ラジカル, カチオン, アニオンは同じ1つの化学種として数え, ラジカルカチオンのように種類の異なる
+/// 開いた原子価を併せ持つ化学種は区別して表せない
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Species<const N: usize, S: Storage> {
+    /// 各炭素の開いた原子価の数
+    pub open_valences: [u32; N],
+    pub bonds: AdjacencyTwoBitsMatrix<N, S>,
+}
+
+impl<const N: usize, S: Storage> Species<N, S> {
+    pub fn num_open_valences(&self) -> usize {
+        self.open_valences.iter().sum::<u32>() as usize
+    }
+
+    pub fn count_hydrogen(&self) -> usize {
+        self.bonds.count_hydrogen() - self.num_open_valences()
+    }
+}
+
+impl<const N: usize, S: Storage> fmt::Display for Species<N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for open_valence in &self.open_valences {
+            write!(f, "{open_valence} ")?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.bonds)
+    }
+}
+
+/// 開いた原子価の総数が `num_open_valences` となるよう骨格の炭素に割り振る方法を,
+/// 骨格の対称性で重複を除いて全て返す. 併せて返す置換は, 割り振りを保つ固定部分群の生成元
+pub fn assign_open_valences<const N: usize, S: Storage>(
+    skeleton: &AdjacencyTwoBitsMatrix<N, S>,
+    stabilizer: &[Permutation<N>],
+    num_open_valences: usize,
+) -> Vec<([u32; N], Vec<Permutation<N>>)> {
+    let fits = |i: usize, open_valence: u32| skeleton.degree_of(i) + open_valence <= 4;
+    partitions(num_open_valences as u32, MAX_OPEN_VALENCE)
+        .into_iter()
+        .filter(|parts| parts.len() <= N)
+        .flat_map(|parts| {
+            let mut open_valences = [0; N];
+            open_valences[..parts.len()].copy_from_slice(&parts);
+            assign_labels(open_valences, stabilizer, fits)
+        })
+        .collect()
+}
+
+// `n` を `max` 以下の正の整数の和に分ける方法を, 非増加の列として全て返す
+fn partitions(n: u32, max: u32) -> Vec<Vec<u32>> {
+    if n == 0 {
+        return vec![Vec::new()];
+    }
+    (1..=max.min(n))
+        .rev()
+        .flat_map(|first| {
+            partitions(n - first, first)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, first);
+                    rest
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::enumeration::{count_isomers, count_species, enumerate_species};
+
+    #[test]
+    fn species() {
+        // 開いた原子価を持たない場合は通常の炭化水素に一致する
+        assert_eq!(
+            count_species::<5>(0, Default::default()).unwrap(),
+            count_isomers::<5>(Default::default()).unwrap(),
+        );
+        // ペンチルカチオン (C5H11+) はペンチル基の数と同じ 8 種類
+        assert_eq!(count_species::<5>(1, Default::default()).unwrap()[11], 8);
+
+        // ブチルラジカル: n-, sec-, イソ-, tert-
+        assert_eq!(count_species::<4>(1, Default::default()).unwrap()[9], 4);
+        // C3H5 ラジカル: 1-プロペニル, イソプロペニル, アリル, シクロプロピル
+        assert_eq!(count_species::<3>(1, Default::default()).unwrap()[5], 4);
+        // C2H4 の開いた原子価 2 つ: エチレンビラジカルとエチリデン
+        let species = enumerate_species::<2>(2, Default::default()).unwrap();
+        let species: Vec<_> = species.iter().filter(|s| s.count_hydrogen() == 4).collect();
+        assert_eq!(species.len(), 2);
+        assert!(species.iter().any(|s| s.open_valences.contains(&2)));
+    }
+}
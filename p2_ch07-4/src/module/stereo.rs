@@ -0,0 +1,428 @@
+use std::collections::VecDeque;
+
+use rustc_hash::FxHashSet;
+
+use super::{
+    error::EnumerationError,
+    matrix::AdjacencyTwoBitsMatrix,
+    permutation::Permutation,
+    plausibility::{Graph, MIN_RING_SIZE_FOR_TRANS},
+    storage::Storage,
+};
+
+/// 配置を反転しうる立体異性の単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StereoUnit {
+    /// 単結合のみを持ち, 水素が高々1つの四面体中心
+    Tetrahedral(usize),
+    /// 両端が置換基を持つ二重結合. 8 員環未満の環に含まれるものはシスに限られるので除く
+    DoubleBond(usize, usize),
+}
+
+/// 構造異性体の立体異性体全体の, 鏡像に関する分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Chirality {
+    /// 全ての立体異性体が鏡像と重なる
+    Achiral,
+    /// 全ての立体異性体が鏡像と重ならない
+    Chiral,
+    /// 鏡像異性体の対と, 鏡像と重なるメソ体がともに存在する
+    Meso,
+}
+
+/// 水素数ごとの, 鏡像に関する分類ごとの構造異性体の数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChiralityCounts {
+    pub achiral: usize,
+    pub chiral: usize,
+    pub meso: usize,
+}
+
+impl ChiralityCounts {
+    pub fn add(&mut self, chirality: Chirality) {
+        match chirality {
+            Chirality::Achiral => self.achiral += 1,
+            Chirality::Chiral => self.chiral += 1,
+            Chirality::Meso => self.meso += 1,
+        }
+    }
+}
+
+/// 立体単位に作用する対称操作の数の上限. 自己同型群そのものではなく,
+/// 立体単位への作用として区別できる操作だけを数える
+pub const MAX_STEREO_ACTIONS: usize = 1 << 16;
+
+// 対称操作が立体単位に及ぼす作用.
+// 単位 i の配置を単位 targets[i] に移し, flips のビットが立つ単位では反転する
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct UnitAction {
+    targets: Vec<usize>,
+    flips: u128,
+}
+
+impl UnitAction {
+    fn identity(num_units: usize) -> Self {
+        Self {
+            targets: (0..num_units).collect(),
+            flips: 0,
+        }
+    }
+
+    // この作用の後に `next` を施す作用
+    fn then(&self, next: &Self) -> Self {
+        let mut flips = 0;
+        for (i, &target) in self.targets.iter().enumerate() {
+            flips |= ((self.flips >> i ^ next.flips >> target) & 1) << i;
+        }
+        Self {
+            targets: self.targets.iter().map(|&t| next.targets[t]).collect(),
+            flips,
+        }
+    }
+
+    fn apply(&self, configuration: u128) -> u128 {
+        let flipped = configuration ^ self.flips;
+        self.targets
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| flipped >> i & 1 == 1)
+            .fold(0, |acc, (_, &target)| acc | 1 << target)
+    }
+
+    // この作用の後に鏡映 (反転 `mirror`) を施したときに不変な配置の数.
+    // 単位の巡回ごとに, 反転の回数が偶数なら2通りの配置が不変になる
+    fn count_fixed(&self, mirror: u128) -> u128 {
+        let flips = self.flips ^ mirror;
+        let mut visited = vec![false; self.targets.len()];
+        let mut count = 1;
+        for start in 0..self.targets.len() {
+            if visited[start] {
+                continue;
+            }
+            let mut parity = 0;
+            let mut i = start;
+            while !visited[i] {
+                visited[i] = true;
+                parity ^= flips >> i & 1;
+                i = self.targets[i];
+            }
+            if parity == 1 {
+                return 0;
+            }
+            count *= 2;
+        }
+        count
+    }
+}
+
+/// 構造異性体の立体異性の解析.
+/// 配置は立体単位ごとに1ビットで表し, 分子の自己同型で移り合う配置を同一視する
+pub struct StereoAnalysis {
+    units: Vec<StereoUnit>,
+    actions: Vec<UnitAction>,
+}
+
+impl StereoAnalysis {
+    /// `automorphisms` は `hydrocarbon` の自己同型群の生成元.
+    /// 小さな多環系で幾何的に実現できない配置は除かない.
+    /// 立体単位を立体単位に移さない置換 (結合次数を保たない骨格の自己同型など) を含む場合や,
+    /// 立体単位への作用として区別できる対称操作が `MAX_STEREO_ACTIONS` を超える場合はエラーになる
+    pub fn new<const N: usize, S: Storage>(
+        hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+        automorphisms: &[Permutation<N>],
+    ) -> Result<Self, EnumerationError> {
+        let neighbors: Vec<Vec<usize>> = (0..N)
+            .map(|u| {
+                (0..N)
+                    .filter(|&v| hydrocarbon.element_at(u, v) != 0)
+                    .collect()
+            })
+            .collect();
+        let units = find_units(hydrocarbon, &neighbors);
+        let mut unit_index = vec![[None; N]; N];
+        for (i, &unit) in units.iter().enumerate() {
+            let (u, v) = match unit {
+                StereoUnit::Tetrahedral(v) => (v, v),
+                StereoUnit::DoubleBond(u, v) => (u, v),
+            };
+            unit_index[u][v] = Some(i);
+            unit_index[v][u] = Some(i);
+        }
+
+        // 二重結合の端 u について, 相手 partner 以外で番号が最小の隣接原子
+        let first_substituent =
+            |u: usize, partner: usize| neighbors[u].iter().copied().find(|&a| a != partner);
+        let generators = automorphisms
+            .iter()
+            .map(|g| -> Result<_, EnumerationError> {
+                let mut targets = Vec::with_capacity(units.len());
+                let mut flips = 0;
+                for (i, &unit) in units.iter().enumerate() {
+                    let (target, flip) = match unit {
+                        StereoUnit::Tetrahedral(v) => (
+                            unit_index[g.apply(v)][g.apply(v)],
+                            is_odd_after(&neighbors[v], g),
+                        ),
+                        StereoUnit::DoubleBond(u, v) => {
+                            // 最小の置換基が最小でなくなる端ごとに, シスとトランスが入れ替わる
+                            let moves_first = |x: usize, y: usize| {
+                                first_substituent(x, y).map(|a| g.apply(a))
+                                    != first_substituent(g.apply(x), g.apply(y))
+                            };
+                            let flip = moves_first(u, v) ^ moves_first(v, u);
+                            (unit_index[g.apply(u)][g.apply(v)], flip)
+                        }
+                    };
+                    targets.push(target.ok_or(EnumerationError::StereoUnitsNotPreserved)?);
+                    if flip {
+                        flips |= 1 << i;
+                    }
+                }
+                Ok(UnitAction { targets, flips })
+            })
+            .collect::<Result<_, _>>()?;
+        let actions = close_actions(units.len(), generators)?;
+        Ok(Self { units, actions })
+    }
+
+    pub fn units(&self) -> &[StereoUnit] {
+        self.units.as_slice()
+    }
+
+    /// 鏡像異性体を区別した立体異性体の数. Burnside の補題で軌道を数える
+    pub fn count(&self) -> usize {
+        self.count_orbits(0)
+    }
+
+    /// 鏡像と重なる立体異性体の数.
+    /// 鏡映を加えた群での軌道の数は (全体 + 鏡像と重なるもの) / 2 に等しい
+    pub fn count_achiral(&self) -> usize {
+        let num_orbits = self.count_orbits(0) + self.count_orbits(self.mirror());
+        num_orbits - self.count()
+    }
+
+    pub fn chirality(&self) -> Chirality {
+        let num_achiral = self.count_achiral();
+        if num_achiral == self.count() {
+            Chirality::Achiral
+        } else if num_achiral == 0 {
+            Chirality::Chiral
+        } else {
+            Chirality::Meso
+        }
+    }
+
+    // 各作用の後に反転 `mirror` を施したときに不変な配置の数の平均
+    fn count_orbits(&self, mirror: u128) -> usize {
+        let num_fixed: u128 = self.actions.iter().map(|a| a.count_fixed(mirror)).sum();
+        (num_fixed / self.actions.len() as u128) as usize
+    }
+
+    // 鏡映は四面体中心の配置を全て反転し, 二重結合のシスとトランスは保つ
+    fn mirror(&self) -> u128 {
+        self.units
+            .iter()
+            .enumerate()
+            .filter(|(_, unit)| matches!(unit, StereoUnit::Tetrahedral(_)))
+            .fold(0, |acc, (i, _)| acc | 1 << i)
+    }
+
+    /// 自己同型で移り合う配置のうち最小のものを, 立体異性体ごとに1つずつ返す.
+    /// 全ての配置を調べるので, 立体単位の数に対して指数的に時間がかかる
+    pub fn configurations(&self) -> Vec<u128> {
+        (0..1u128 << self.units.len())
+            .filter(|&c| self.actions.iter().all(|action| action.apply(c) >= c))
+            .collect()
+    }
+}
+
+// 生成元の作用から, 立体単位に作用する群の元を全て作る.
+// 立体単位を動かさない自己同型 (メチル基の入れ替えなど) は恒等作用にまとまるので,
+// 自己同型群そのものより小さい
+fn close_actions(
+    num_units: usize,
+    generators: Vec<UnitAction>,
+) -> Result<Vec<UnitAction>, EnumerationError> {
+    let identity = UnitAction::identity(num_units);
+    let mut group = FxHashSet::default();
+    group.insert(identity.clone());
+    let mut queue = VecDeque::from([identity]);
+    while let Some(action) = queue.pop_front() {
+        for generator in &generators {
+            let composed = action.then(generator);
+            if !group.contains(&composed) {
+                if group.len() == MAX_STEREO_ACTIONS {
+                    return Err(EnumerationError::TooManySymmetries {
+                        max: MAX_STEREO_ACTIONS,
+                    });
+                }
+                group.insert(composed.clone());
+                queue.push_back(composed);
+            }
+        }
+    }
+    Ok(group.into_iter().collect())
+}
+
+fn find_units<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+    neighbors: &[Vec<usize>],
+) -> Vec<StereoUnit> {
+    let graph = Graph::<N, S>::from(hydrocarbon);
+    let mut units = Vec::new();
+    for (u, neighbors_u) in neighbors.iter().enumerate() {
+        // 水素が2つ以上あると, それらを入れ替えても同じ分子になる
+        if hydrocarbon.count_multiple_bonds_of(u) == 0 && neighbors_u.len() >= 3 {
+            units.push(StereoUnit::Tetrahedral(u));
+        }
+    }
+    for u in 0..N {
+        for &v in neighbors[u].iter().filter(|&&v| v > u) {
+            // 両端がこの二重結合以外に多重結合を持たず, 水素以外の置換基を持つ
+            let is_candidate = hydrocarbon.element_at(u, v) == 2
+                && [u, v].iter().all(|&x| {
+                    hydrocarbon.count_multiple_bonds_of(x) == 1 && neighbors[x].len() >= 2
+                })
+                && graph
+                    .shortest_ring_through(u, v)
+                    .is_none_or(|size| size >= MIN_RING_SIZE_FOR_TRANS);
+            if is_candidate {
+                units.push(StereoUnit::DoubleBond(u, v));
+            }
+        }
+    }
+    units
+}
+
+// 隣接原子の並びを g で移した先で, 番号順に並べ替える置換が奇置換か
+fn is_odd_after<const N: usize>(neighbors: &[usize], g: &Permutation<N>) -> bool {
+    let mapped: Vec<_> = neighbors.iter().map(|&a| g.apply(a)).collect();
+    let num_inversions = (0..mapped.len())
+        .flat_map(|i| (i + 1..mapped.len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| mapped[i] > mapped[j])
+        .count();
+    num_inversions % 2 == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            enumeration::{count_chirality, count_stereoisomers, enumerate_stereoisomers},
+            storage::Storage16,
+        },
+        *,
+    };
+
+    // 単位 0..n の互換と巡回置換. 反転 `flips` を併せて施す
+    fn symmetric_actions(n: usize, flips: u128) -> Vec<UnitAction> {
+        let mut cycle: Vec<_> = (0..n).collect();
+        cycle.rotate_left(1);
+        let mut swap: Vec<_> = (0..n).collect();
+        swap.swap(0, 1);
+        vec![
+            UnitAction {
+                targets: swap,
+                flips,
+            },
+            UnitAction {
+                targets: cycle,
+                flips: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn close_actions() {
+        let actions = super::close_actions(3, Vec::new()).unwrap();
+        assert_eq!(actions, vec![UnitAction::identity(3)]);
+        assert_eq!(
+            super::close_actions(4, symmetric_actions(4, 0))
+                .unwrap()
+                .len(),
+            24
+        );
+        // 反転を伴う入れ替えで, 符号付き置換群 2^4 × S_4 になる
+        assert_eq!(
+            super::close_actions(4, symmetric_actions(4, 1))
+                .unwrap()
+                .len(),
+            384
+        );
+        // 9! は上限を超える
+        assert_eq!(
+            super::close_actions(9, symmetric_actions(9, 0)),
+            Err(EnumerationError::TooManySymmetries {
+                max: MAX_STEREO_ACTIONS
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_non_automorphisms() {
+        let butene: AdjacencyTwoBitsMatrix<4, Storage16> = "CC=CC".parse().unwrap();
+        let reversal = Permutation::new([3, 2, 1, 0]);
+        let analysis = StereoAnalysis::new(&butene, &[reversal]).unwrap();
+        assert_eq!(analysis.count(), 2);
+        // 末端のメチル基と二重結合の炭素を入れ替えると, 二重結合は立体単位でない結合に移る
+        let swap = Permutation::new([1, 0, 2, 3]);
+        assert_eq!(
+            StereoAnalysis::new(&butene, &[swap]).err(),
+            Some(EnumerationError::StereoUnitsNotPreserved)
+        );
+    }
+
+    #[test]
+    fn stereoisomers() {
+        // アルカンの立体異性体の数 (鏡像異性体を区別する)
+        assert_eq!(
+            count_stereoisomers::<7>(Default::default()).unwrap()[16],
+            11
+        );
+        assert_eq!(
+            count_stereoisomers::<8>(Default::default()).unwrap()[18],
+            24
+        );
+        // 2-ブテンの E/Z と, 1,2-ジメチルシクロプロパンのシス体とトランス体の鏡像異性体
+        assert_eq!(count_stereoisomers::<4>(Default::default()).unwrap()[8], 6);
+        assert_eq!(
+            count_stereoisomers::<5>(Default::default()).unwrap()[10],
+            13
+        );
+
+        for (hydrocarbon, analysis) in enumerate_stereoisomers::<6>(Default::default()).unwrap() {
+            assert_eq!(
+                analysis.configurations().len(),
+                analysis.count(),
+                "{hydrocarbon}"
+            );
+        }
+    }
+
+    #[test]
+    fn chirality() {
+        // 3,4-ジメチルヘキサンのみメソ体を持つ
+        let counts = count_chirality::<8>(Default::default()).unwrap()[18];
+        let expected = ChiralityCounts {
+            achiral: 13,
+            chiral: 4,
+            meso: 1,
+        };
+        assert_eq!(counts, expected);
+        // シス-1,2-ジメチルシクロプロパンはメソ体
+        let counts = count_chirality::<5>(Default::default()).unwrap()[10];
+        let expected = ChiralityCounts {
+            achiral: 9,
+            chiral: 0,
+            meso: 1,
+        };
+        assert_eq!(counts, expected);
+
+        for (hydrocarbon, analysis) in enumerate_stereoisomers::<6>(Default::default()).unwrap() {
+            let num_achiral = analysis.count_achiral();
+            assert!(num_achiral <= analysis.count(), "{hydrocarbon}");
+            // 鏡像と重ならないものは対になって現れる
+            assert_eq!((analysis.count() - num_achiral) % 2, 0, "{hydrocarbon}");
+        }
+    }
+}
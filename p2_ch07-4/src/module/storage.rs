@@ -0,0 +1,200 @@
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{
+        Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl,
+        Shr, Sub,
+    },
+};
+
+/// 行列の1行分を格納する符号なし整数
+pub trait Bits:
+    Copy
+    + Eq
+    + Ord
+    + Hash
+    + Debug
+    + Display
+    + Default
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + BitAnd<Output = Self>
+    + BitAndAssign
+    + BitOr<Output = Self>
+    + BitOrAssign
+    + BitXor<Output = Self>
+    + BitXorAssign
+    + Not<Output = Self>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const MAX: Self;
+    const BITS: usize;
+    /// 0b0101...0101
+    const EVEN_BITS: Self;
+
+    /// (次数, 行) の組を, 辞書式順序を保ったまま1つの値にまとめたもの
+    type Fat: Copy + Ord;
+
+    fn fat(count: u32, row: Self) -> Self::Fat;
+
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    /// 下位 32 ビットを切り出す
+    fn low_u32(self) -> u32;
+
+    /// 下位 n ビットが全て 1 の値
+    fn repunit(n: usize) -> Self {
+        if n == 0 {
+            Self::ZERO
+        } else {
+            Self::MAX >> (Self::BITS - n)
+        }
+    }
+}
+
+macro_rules! impl_bits {
+    ($($t:ty => $fat:ty),*) => {
+        $(
+            impl Bits for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MAX: Self = <$t>::MAX;
+                const BITS: usize = <$t>::BITS as usize;
+                const EVEN_BITS: Self = <$t>::MAX / 3;
+
+                type Fat = $fat;
+
+                fn fat(count: u32, row: Self) -> Self::Fat {
+                    (count as $fat) << <$t>::BITS | row as $fat
+                }
+
+                fn count_ones(self) -> u32 {
+                    <$t>::count_ones(self)
+                }
+
+                fn trailing_zeros(self) -> u32 {
+                    <$t>::trailing_zeros(self)
+                }
+
+                fn low_u32(self) -> u32 {
+                    self as u32
+                }
+            }
+        )*
+    };
+}
+
+impl_bits!(u16 => u32, u32 => u64, u64 => u128);
+
+// u128 は2ビット行列の行としてのみ用いるため, fat は組のままでよい
+impl Bits for u128 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    const MAX: Self = u128::MAX;
+    const BITS: usize = u128::BITS as usize;
+    const EVEN_BITS: Self = u128::MAX / 3;
+
+    type Fat = (u32, u128);
+
+    fn fat(count: u32, row: Self) -> Self::Fat {
+        (count, row)
+    }
+
+    fn count_ones(self) -> u32 {
+        u128::count_ones(self)
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        u128::trailing_zeros(self)
+    }
+
+    fn low_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+/// 隣接行列の格納方式. 炭素数に応じて行の幅を選ぶ
+pub trait Storage: Debug + Clone + Copy + PartialEq + Eq + Hash + Send + Sync + 'static {
+    /// `AdjacencyBitMatrix` の行 (1列あたり1ビット)
+    type Row: Bits;
+    /// `AdjacencyTwoBitsMatrix` の行 (1列あたり2ビット)
+    type TwoBitsRow: Bits;
+}
+
+macro_rules! define_storage {
+    ($($name:ident: $row:ty, $two_bits_row:ty);*) => {
+        $(
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            pub struct $name;
+
+            impl Storage for $name {
+                type Row = $row;
+                type TwoBitsRow = $two_bits_row;
+            }
+        )*
+    };
+}
+
+define_storage!(
+    Storage16: u16, u32;
+    Storage32: u32, u64;
+    Storage64: u64, u128
+);
+
+/// 炭素数を型として表したもの
+pub struct Carbon<const N: usize>;
+
+/// 炭素数 `N` に対して十分な幅を持つ格納方式を選ぶ
+pub trait SelectStorage {
+    type Storage: Storage;
+}
+
+pub type StorageOf<const N: usize> = <Carbon<N> as SelectStorage>::Storage;
+
+macro_rules! impl_select_storage {
+    ($storage:ty; $($n:literal),+) => {
+        $(
+            impl SelectStorage for Carbon<$n> {
+                type Storage = $storage;
+            }
+        )+
+    };
+}
+
+impl_select_storage!(Storage16; 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+impl_select_storage!(
+    Storage32; 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32
+);
+impl_select_storage!(
+    Storage64; 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53,
+    54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{matrix::AdjacencyTwoBitsMatrix, smiles::to_smiles},
+        *,
+    };
+
+    // 33 炭素以上の行列は Storage64 で表す
+    #[test]
+    fn storage64() {
+        let smiles = format!("C1CC({})CC1=C=CC#CC", "C".repeat(30));
+        let hydrocarbon: AdjacencyTwoBitsMatrix<40, Storage64> = smiles.parse().unwrap();
+        assert_eq!(hydrocarbon.count_hydrogen(), 72);
+        let canonical = to_smiles(&hydrocarbon);
+        let reparsed: AdjacencyTwoBitsMatrix<40, Storage64> = canonical.parse().unwrap();
+        assert_eq!(to_smiles(&reparsed), canonical);
+        let sparse6 = hydrocarbon.to_sparse6();
+        let decoded = AdjacencyTwoBitsMatrix::<40, Storage64>::from_sparse6(&sparse6).unwrap();
+        assert_eq!(decoded, hydrocarbon);
+    }
+}
@@ -0,0 +1,199 @@
+use std::array;
+
+use rustc_hash::FxHashSet;
+
+use super::{
+    error::EnumerationError,
+    matrix::AdjacencyTwoBitsMatrix,
+    permutation::{calc_orbit, group_order, Permutation},
+    storage::Storage,
+};
+
+/// 水素を同位体や置換基に置き換える位置のパターン. 同じ炭素に結合する水素は区別しない
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Substitution<const N: usize> {
+    /// 各炭素で置き換える水素の数
+    pub counts: [u32; N],
+    /// このパターンになる水素の選び方の数. 全てのパターンでの和は (水素数) C (置き換える数) に等しい
+    pub multiplicity: u128,
+}
+
+/// 炭化水素の自己同型群と, 各炭素に結合する水素の数
+pub struct Symmetry<const N: usize> {
+    automorphisms: Vec<Permutation<N>>,
+    hydrogens: [u32; N],
+}
+
+impl<const N: usize> Symmetry<N> {
+    /// `automorphisms` は `hydrocarbon` の自己同型群の生成元
+    pub fn new<S: Storage>(
+        hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+        automorphisms: &[Permutation<N>],
+    ) -> Self {
+        Self {
+            automorphisms: automorphisms.to_vec(),
+            hydrogens: array::from_fn(|i| 4 - hydrocarbon.degree_of(i)),
+        }
+    }
+
+    /// 自己同型群の位数. 炭素の置換のみを数え, 同じ炭素の水素の入れ替えは含めない.
+    /// 群の元は列挙しないので, 位数が大きくても生成元の数と頂点数の多項式時間で求まる
+    pub fn order(&self) -> u128 {
+        group_order(&self.automorphisms)
+    }
+
+    pub fn num_hydrogens(&self) -> usize {
+        self.hydrogens.iter().sum::<u32>() as usize
+    }
+
+    /// 水素を `k` 個置き換える位置のパターンを, 自己同型で移り合うものを除いて全て返す.
+    /// 例えば重水素化体 C_n H_(m-k) D_k の異性体が得られる.
+    /// 自己同型が Kekulé 構造を区別する場合, 芳香環では結合次数の異なる位置を別のものとして数える
+    pub fn substitutions(&self, k: usize) -> Result<Vec<Substitution<N>>, EnumerationError> {
+        let remaining =
+            u32::try_from(k).map_err(|_| EnumerationError::TooManySubstitutions { count: k })?;
+        let mut substitutions = Vec::new();
+        let mut seen_orbits = FxHashSet::default();
+        let mut counts = [0; N];
+        self.distribute(0, remaining, &mut counts, &mut |counts| {
+            if seen_orbits.contains(counts) {
+                return;
+            }
+            let orbit: Vec<_> = calc_orbit(*counts, &self.automorphisms)
+                .into_iter()
+                .collect();
+            let num_choices: u128 = (0..N)
+                .map(|i| binomial(self.hydrogens[i], counts[i]))
+                .product();
+            substitutions.push(Substitution {
+                counts: *counts,
+                multiplicity: orbit.len() as u128 * num_choices,
+            });
+            seen_orbits.extend(orbit);
+        });
+        Ok(substitutions)
+    }
+
+    // 炭素 `i` 以降に, 水素の数を超えないよう `remaining` 個の置換を割り振る
+    fn distribute(
+        &self,
+        i: usize,
+        remaining: u32,
+        counts: &mut [u32; N],
+        visit: &mut impl FnMut(&[u32; N]),
+    ) {
+        if i == N {
+            if remaining == 0 {
+                visit(counts);
+            }
+            return;
+        }
+        for count in 0..=remaining.min(self.hydrogens[i]) {
+            counts[i] = count;
+            self.distribute(i + 1, remaining - count, counts, visit);
+        }
+        counts[i] = 0;
+    }
+}
+
+fn binomial(n: u32, k: u32) -> u128 {
+    (0..k as u128).fold(1, |acc, i| acc * (n as u128 - i) / (i + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            aromaticity::is_aromatic,
+            element::Formula,
+            enumeration::{
+                count_substitution_isomers, enumerate_formula, enumerate_substitution_isomers,
+                enumerate_with_symmetry, EnumerationConfig,
+            },
+        },
+        *,
+    };
+
+    #[test]
+    fn isotopologues() {
+        let config = EnumerationConfig {
+            num_hydrogens: Some(8),
+            ..Default::default()
+        };
+        let propanes = enumerate_with_symmetry::<3>(config).unwrap();
+        let [(_, symmetry)] = propanes.as_slice() else {
+            panic!("C3H8 should be propane only");
+        };
+        assert_eq!(symmetry.order(), 2);
+        // 1-D と 2-D
+        let multiplicities: Vec<_> = symmetry
+            .substitutions(1)
+            .unwrap()
+            .iter()
+            .map(|s| s.multiplicity)
+            .collect();
+        assert_eq!(multiplicities.len(), 2);
+        assert!(multiplicities.contains(&6) && multiplicities.contains(&2));
+        // 1,1-, 1,2-, 1,3-, 2,2-D2
+        let substitutions = symmetry.substitutions(2).unwrap();
+        assert_eq!(substitutions.len(), 4);
+        assert_eq!(
+            substitutions.iter().map(|s| s.multiplicity).sum::<u128>(),
+            28
+        );
+        assert_eq!(symmetry.substitutions(8).unwrap().len(), 1);
+        assert!(symmetry.substitutions(9).unwrap().is_empty());
+        assert_eq!(
+            symmetry.substitutions(usize::MAX),
+            Err(EnumerationError::TooManySubstitutions { count: usize::MAX })
+        );
+
+        // ベンゼンの二置換体は o-, m-, p- の3種類
+        let config = EnumerationConfig {
+            num_hydrogens: Some(6),
+            filters: vec![Box::new(is_aromatic)],
+            collapse_resonance: true,
+            ..Default::default()
+        };
+        let benzenes = enumerate_with_symmetry::<6>(config).unwrap();
+        let [(_, symmetry)] = benzenes.as_slice() else {
+            panic!("benzene should be the only aromatic C6H6");
+        };
+        assert_eq!(symmetry.order(), 12);
+        assert_eq!(symmetry.substitutions(2).unwrap().len(), 3);
+        assert_eq!(symmetry.substitutions(3).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn substitution_isomers() {
+        // 置換体の数は, 置換基を1価の元素とみなした分子式の異性体の数に一致する
+        let count = |k, num_h| {
+            let config = EnumerationConfig {
+                num_hydrogens: Some(num_h),
+                ..Default::default()
+            };
+            count_substitution_isomers::<4>(k, config).unwrap()[num_h]
+        };
+        let count_formula = |formula: &str| {
+            let formula: Formula = formula.parse().unwrap();
+            match formula.num_heavy_atoms() {
+                5 => enumerate_formula::<5>(&formula, Default::default())
+                    .unwrap()
+                    .len(),
+                6 => enumerate_formula::<6>(&formula, Default::default())
+                    .unwrap()
+                    .len(),
+                _ => unreachable!(),
+            }
+        };
+        assert_eq!(count(1, 10), count_formula("C4H9Cl"));
+        assert_eq!(count(1, 8), count_formula("C4H7Cl"));
+        assert_eq!(count(2, 10), count_formula("C4H8Cl2"));
+        assert_eq!(count(2, 10), 9);
+
+        let substituted = enumerate_substitution_isomers::<4>(2, Default::default()).unwrap();
+        let total: usize = substituted.iter().map(|(_, s)| s.len()).sum();
+        let counts = count_substitution_isomers::<4>(2, Default::default()).unwrap();
+        assert_eq!(total, counts.iter().sum::<usize>());
+    }
+}
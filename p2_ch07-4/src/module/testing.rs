@@ -0,0 +1,41 @@
+use super::{
+    enumeration::{count_isomers, EnumerationConfig},
+    matrix::AdjacencyTwoBitsMatrix,
+    storage::{Carbon, SelectStorage, Storage, StorageOf},
+};
+
+/// 結合次数によらない骨格の辺の数
+pub fn count_edges<const N: usize, S: Storage>(
+    hydrocarbon: &AdjacencyTwoBitsMatrix<N, S>,
+) -> usize {
+    (0..N)
+        .flat_map(|i| (i + 1..N).map(move |j| (i, j)))
+        .filter(|&(i, j)| hydrocarbon.element_at(i, j) != 0)
+        .count()
+}
+
+/// `configure` で設定した列挙が, 全列挙を `filter` で絞り込んだ結果と水素数ごとに一致することを確かめ,
+/// その個数を返す. `context` は失敗時に表示する設定の説明
+pub fn assert_same_counts_as_filter<const N: usize>(
+    filter: impl Fn(&AdjacencyTwoBitsMatrix<N, StorageOf<N>>) -> bool + Send + Sync + 'static,
+    configure: impl FnOnce(&mut EnumerationConfig<N>),
+    context: &str,
+) -> Vec<usize>
+where
+    Carbon<N>: SelectStorage,
+{
+    let config = EnumerationConfig {
+        filters: vec![Box::new(filter)],
+        ..Default::default()
+    };
+    let expected = count_isomers::<N>(config).unwrap();
+
+    let mut config = EnumerationConfig::default();
+    configure(&mut config);
+    assert_eq!(
+        count_isomers::<N>(config).unwrap(),
+        expected,
+        "C = {N}, {context}"
+    );
+    expected
+}
@@ -0,0 +1,80 @@
+//! 全章に共通する異性体数の参照表と, 各章の回帰テストから呼ぶ検査.
+//! 各章のクレートは dev-dependency としてこのクレートを使い, 自身の列挙結果を参照表と比べる
+
+// 炭素数, 水素数, 異性体の数を並べた参照表
+const ISOMER_COUNTS: &str = include_str!("../isomer_counts.csv");
+
+/// 炭素数 `num_carbons` の異性体の数を, 水素数を添字とする表で返す.
+/// 参照表にない炭素数では全て 0 になる
+pub fn reference_counts(num_carbons: usize) -> Vec<usize> {
+    let mut counts = vec![0; 2 * num_carbons + 3];
+    for line in ISOMER_COUNTS.lines().skip(1) {
+        let row: Vec<usize> = line.split(',').map(|x| x.parse().unwrap()).collect();
+        if row[0] == num_carbons {
+            counts[row[1]] = row[2];
+        }
+    }
+    counts
+}
+
+/// 列挙した炭素数 `num_carbons` の異性体の水素数 `hydrogens` を数え, 参照表と一致することを確かめる
+pub fn assert_reference_counts(num_carbons: usize, hydrogens: impl IntoIterator<Item = usize>) {
+    let mut counts = vec![0; 2 * num_carbons + 3];
+    for num_h in hydrogens {
+        counts[num_h] += 1;
+    }
+    assert_eq!(counts, reference_counts(num_carbons), "C = {num_carbons}");
+}
+
+/// 炭素数ごとに参照表と比べるテストを作る. `$check::<N>()` が炭素数 N の検査を行う.
+///
+/// 古い章は全ての行列を生成するため, 炭素数とともに指数的に遅くなる.
+/// `release` に挙げた炭素数はデバッグビルドでは無視し, `cargo test --release` で確認する.
+/// `slow` に挙げた炭素数はリリースビルドでも数分以上かかるかメモリが足りなくなるので常に無視し,
+/// `cargo test --release -- --ignored` で確認する
+#[macro_export]
+macro_rules! golden_tests {
+    (
+        $check:ident => [$($test:ident = $n:literal),+]
+        $(, release [$($release_test:ident = $release:literal),+])?
+        $(, slow [$($slow_test:ident = $slow:literal),+])?
+        $(,)?
+    ) => {
+        $(
+            #[test]
+            fn $test() {
+                $check::<$n>();
+            }
+        )+
+
+        $($(
+            #[test]
+            #[cfg_attr(debug_assertions, ignore = "slow in debug builds")]
+            fn $release_test() {
+                $check::<$release>();
+            }
+        )+)?
+
+        $($(
+            #[test]
+            #[ignore = "slow"]
+            fn $slow_test() {
+                $check::<$slow>();
+            }
+        )+)?
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_table() {
+        // エタン, エチレン, アセチレン
+        assert_eq!(reference_counts(2), vec![0, 0, 1, 0, 1, 0, 1]);
+        // 参照表にない炭素数
+        assert_eq!(reference_counts(11), vec![0; 25]);
+        assert_reference_counts(2, [6, 4, 2]);
+    }
+}